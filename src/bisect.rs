@@ -0,0 +1,306 @@
+use crate::config::Config;
+use crate::environment::{git, Environment, GitInfo};
+use crate::printer::Printer;
+use crate::process::process;
+use crate::runner::Runner;
+use crate::server::{shell, Server, STARTUP_WAIT};
+use crate::snapshot::Snapshot;
+use crate::store::{self, SnapshotStore};
+use anyhow::{anyhow, Context, Result};
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::time::Duration;
+
+// Without a readiness url the server gets this long to start.
+
+pub struct BisectOptions {
+    pub good: String,
+    pub bad: String,
+    pub build: String,
+    pub start: String,
+    pub ready: Option<String>,
+    pub ready_timeout: u64,
+    pub endpoints: Vec<String>,
+}
+
+enum Outcome {
+    Good,
+    Bad,
+    Skip(String),
+}
+
+/// A linked git worktree the commits are checked out in, so the working
+/// directory of the user is left alone. Removed when dropped.
+struct Worktree {
+    path: PathBuf,
+}
+
+impl Worktree {
+    fn add(commit: &str) -> Result<Self> {
+        let path = std::env::temp_dir().join(format!("ballast-bisect-{}", std::process::id()));
+        git(&[
+            "worktree",
+            "add",
+            "--detach",
+            &path.to_string_lossy(),
+            commit,
+        ])
+        .ok_or_else(|| anyhow!("Failed to create a git worktree at {}", path.display()))?;
+        Ok(Self { path })
+    }
+
+    fn checkout(&self, commit: &str) -> Result<()> {
+        git(&[
+            "-C",
+            &self.path.to_string_lossy(),
+            "checkout",
+            "--quiet",
+            "--detach",
+            commit,
+        ])
+        .ok_or_else(|| anyhow!("Failed to check out {}", commit))?;
+        Ok(())
+    }
+}
+
+impl Drop for Worktree {
+    fn drop(&mut self) {
+        git(&[
+            "worktree",
+            "remove",
+            "--force",
+            &self.path.to_string_lossy(),
+        ]);
+    }
+}
+
+fn resolve(reference: &str) -> Result<String> {
+    git(&[
+        "rev-parse",
+        "--verify",
+        &format!("{}^{{commit}}", reference),
+    ])
+    .ok_or_else(|| anyhow!("Unknown git ref {}", reference))
+}
+
+fn describe(commit: &str) -> String {
+    git(&["log", "-1", "--format=%h %s", commit]).unwrap_or_else(|| commit.to_string())
+}
+
+struct Bisect<'a> {
+    options: &'a BisectOptions,
+    config: Config,
+    store: Box<dyn SnapshotStore>,
+    worktree: Worktree,
+    printer: &'a Printer,
+}
+
+impl Bisect<'_> {
+    /// Builds, starts and runs the load against `commit`, and saves its
+    /// snapshot. Returns why when the commit can't be tested.
+    async fn run(
+        &self,
+        commit: &str,
+        baseline: Option<&Snapshot>,
+    ) -> Result<std::result::Result<Snapshot, String>> {
+        self.worktree.checkout(commit)?;
+
+        let build = shell(&self.options.build)
+            .current_dir(&self.worktree.path)
+            .output()
+            .with_context(|| format!("Failed to run {}", self.options.build))?;
+        if !build.status.success() {
+            let stderr = String::from_utf8_lossy(&build.stderr);
+            let last_line = stderr.lines().last().unwrap_or_default().to_string();
+            return Ok(Err(format!("build failed: {}", last_line)));
+        }
+
+        let mut server = Server::start(&self.options.start, &self.worktree.path)?;
+        match &self.options.ready {
+            Some(url) => {
+                let timeout = Duration::from_secs(self.options.ready_timeout);
+                if let Err(e) = server.wait_ready(url, timeout).await {
+                    return Ok(Err(e.to_string()));
+                }
+            }
+            None => tokio::time::sleep(STARTUP_WAIT).await,
+        }
+
+        let loads = Runner::new(self.config.clone())?.run(self.printer).await?;
+        server.stop();
+
+        let tests = process(&loads, &self.config, baseline);
+        let mut environment = Environment::capture(&self.config);
+        environment.git = Some(GitInfo {
+            commit: commit.to_string(),
+            branch: "HEAD".to_string(),
+            dirty: false,
+        });
+        let snapshot = Snapshot::new(
+            tests,
+            Some(format!("bisect {}", describe(commit))),
+            environment,
+        )?;
+        self.store.write(&snapshot)?;
+        for warning in self.store.take_warnings() {
+            self.printer.print_with_yellow("Warning", &warning, 0);
+        }
+
+        Ok(Ok(snapshot))
+    }
+
+    async fn test(&self, commit: &str, baseline: &Snapshot) -> Result<Outcome> {
+        self.printer
+            .blank_line()
+            .print_with_yellow("Testing", &describe(commit), 0);
+        let outcome = match self.run(commit, Some(baseline)).await? {
+            Ok(snapshot) => match snapshot.tests.iter().all(|t| t.success) {
+                true => Outcome::Good,
+                false => Outcome::Bad,
+            },
+            Err(reason) => Outcome::Skip(reason),
+        };
+
+        match &outcome {
+            Outcome::Good => self.printer.print_with_green("Good", &describe(commit), 0),
+            Outcome::Bad => self.printer.print_with_red("Bad", &describe(commit), 0),
+            Outcome::Skip(reason) => self.printer.print_with_yellow(
+                "Skipped",
+                &format!("{}, {}", describe(commit), reason),
+                0,
+            ),
+        };
+        Ok(outcome)
+    }
+}
+
+/// Finds the first commit between `good` and `bad` whose tests fail against
+/// the snapshot of `good`, testing as few commits as possible.
+pub async fn bisect(options: BisectOptions, mut config: Config, printer: &Printer) -> Result<()> {
+    let good = resolve(&options.good)?;
+    let bad = resolve(&options.bad)?;
+
+    if !options.endpoints.is_empty() {
+        for name in &options.endpoints {
+            if !config.endpoints.iter().any(|e| &e.name == name) {
+                return Err(anyhow!("No test named {} in ballast.json", name));
+            }
+        }
+        config
+            .endpoints
+            .retain(|e| options.endpoints.contains(&e.name));
+    }
+
+    // Every commit after good up to and including bad, oldest first.
+    let commits = git(&[
+        "rev-list",
+        "--ancestry-path",
+        "--reverse",
+        &format!("{}..{}", good, bad),
+    ])
+    .ok_or_else(|| anyhow!("Failed to list the commits between good and bad"))?
+    .lines()
+    .map(|c| c.to_string())
+    .collect::<Vec<_>>();
+    if commits.is_empty() {
+        return Err(anyhow!(
+            "{} is not an ancestor of {}",
+            options.good,
+            options.bad
+        ));
+    }
+
+    let bisect = Bisect {
+        options: &options,
+        store: store::open(&config)?,
+        config,
+        worktree: Worktree::add(&good)?,
+        printer,
+    };
+
+    printer.print_with_yellow("Testing", &format!("{} (good)", describe(&good)), 0);
+    let baseline = match bisect.run(&good, None).await? {
+        Ok(snapshot) => snapshot,
+        Err(reason) => return Err(anyhow!("Unable to test the good commit, {}", reason)),
+    };
+    printer.print_with_green("Baseline", &describe(&good), 0);
+
+    match bisect.test(&bad, &baseline).await? {
+        Outcome::Bad => {}
+        Outcome::Good => {
+            printer.blank_line().print_with_green(
+                "Done",
+                &format!("{} isn't a regression of {}", options.bad, options.good),
+                0,
+            );
+            return Ok(());
+        }
+        Outcome::Skip(reason) => {
+            return Err(anyhow!("Unable to test the bad commit, {}", reason));
+        }
+    }
+
+    // Indexes into commits, the last good commit is -1 (good itself) and the
+    // first bad one is the last commit (bad itself) until proven otherwise.
+    let mut last_good: isize = -1;
+    let mut first_bad = commits.len() as isize - 1;
+    let mut skipped = HashSet::new();
+    loop {
+        let middle = (last_good + first_bad) / 2;
+        let next = (last_good + 1..first_bad)
+            .filter(|i| !skipped.contains(i))
+            .min_by_key(|i| (i - middle).abs());
+        let next = match next {
+            Some(next) => next,
+            None => break,
+        };
+
+        printer.print_with_yellow(
+            "Bisecting",
+            &format!(
+                "{} commits left to test (roughly {} steps)",
+                first_bad - last_good - 1,
+                ((first_bad - last_good) as f64).log2().ceil()
+            ),
+            0,
+        );
+        match bisect.test(&commits[next as usize], &baseline).await? {
+            Outcome::Good => last_good = next,
+            Outcome::Bad => first_bad = next,
+            Outcome::Skip(_) => {
+                skipped.insert(next);
+            }
+        }
+    }
+
+    let untested = (last_good + 1..first_bad)
+        .map(|i| describe(&commits[i as usize]))
+        .collect::<Vec<_>>();
+    printer.blank_line();
+    match untested.is_empty() {
+        true => printer.print_with_red(
+            "Found",
+            &format!(
+                "first bad commit {}",
+                describe(&commits[first_bad as usize])
+            ),
+            0,
+        ),
+        false => {
+            printer.print_with_red(
+                "Found",
+                &format!(
+                    "first bad commit is {} or one of the skipped commits before it",
+                    describe(&commits[first_bad as usize])
+                ),
+                0,
+            );
+            for commit in untested {
+                printer.print_plain(&commit, 4);
+            }
+            printer
+        }
+    };
+
+    Ok(())
+}
@@ -1,8 +1,216 @@
-use clap::{Command, arg};
-pub fn cli() -> Command {
-    Command::new("ballast")
-        .subcommand_required(false)
-        .about("A simple cli tool to run snapshot performance tests incrementally against local apis")
-        .flag(arg!("--no-snapshot"))
-        .arg(arg!("--desc <desc>"))
+use crate::report::ReportTarget;
+use crate::samples::SamplesTarget;
+use clap::{Parser, Subcommand, ValueEnum};
+
+#[derive(Parser)]
+#[command(
+    name = "ballast",
+    version,
+    about = "A simple cli tool to run snapshot performance tests incrementally against local apis"
+)]
+pub struct Args {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    #[arg(long = "no-snapshot", required = false)]
+    pub no_snapshot: bool,
+
+    /// Write a report of the run, e.g. junit=report.xml (can be repeated)
+    #[arg(long = "report", value_name = "FORMAT=PATH")]
+    pub reports: Vec<ReportTarget>,
+
+    /// Compare against the latest snapshot of a commit, tag or branch
+    #[arg(long, value_name = "REF")]
+    pub against: Option<String>,
+
+    /// Compare against a snapshot exported with `ballast snapshot export`
+    #[arg(long, value_name = "PATH", conflicts_with = "against")]
+    pub baseline_file: Option<String>,
+
+    /// Run the load of every test N times, to measure how much it varies
+    #[arg(long, value_name = "N", default_value_t = 1)]
+    pub repeat: u64,
+
+    /// A description of the run, saved with its snapshot
+    #[arg(long)]
+    pub desc: Option<String>,
+
+    /// Serve prometheus metrics of the run on ADDR/metrics while it runs
+    #[arg(long, value_name = "ADDR")]
+    pub metrics_addr: Option<std::net::SocketAddr>,
+
+    /// Write the metrics of the run for the node exporter textfile collector
+    #[arg(long, value_name = "PATH")]
+    pub metrics_file: Option<String>,
+
+    /// Write every request of the run to a .csv or .jsonl file
+    #[arg(long, value_name = "PATH")]
+    pub samples: Option<SamplesTarget>,
+
+    /// Output format of the results on stdout, progress goes to stderr with json
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+    pub format: OutputFormat,
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+pub enum OutputFormat {
+    Text,
+    Json,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Generate tests in ballast.json from an existing api description
+    Import {
+        #[command(subcommand)]
+        source: ImportSource,
+
+        /// The config file the imported tests are added to
+        #[arg(long, global = true, default_value = "./ballast.json")]
+        output: String,
+
+        /// Concurrent requests per cycle of the imported tests
+        #[arg(long, global = true, default_value_t = 5)]
+        concurrent_requests: u64,
+
+        /// Cycles of the imported tests
+        #[arg(long, global = true, default_value_t = 10)]
+        cycles: u64,
+    },
+    /// Find the commit that introduced a regression by running the tests
+    /// against the commits between a good and a bad one
+    Bisect {
+        /// A commit without the regression
+        #[arg(long)]
+        good: String,
+
+        /// A commit with the regression
+        #[arg(long)]
+        bad: String,
+
+        /// Command that builds the service, run in the checked out commit
+        #[arg(long)]
+        build: String,
+
+        /// Command that starts the service, run in the checked out commit
+        #[arg(long)]
+        start: String,
+
+        /// Url that answers once the service is ready, otherwise it gets 5 seconds
+        #[arg(long, value_name = "URL")]
+        ready: Option<String>,
+
+        /// Seconds to wait for the ready url
+        #[arg(long, default_value_t = 60)]
+        ready_timeout: u64,
+
+        /// Only run these tests (can be repeated)
+        #[arg(long = "endpoint", value_name = "NAME")]
+        endpoints: Vec<String>,
+    },
+    /// List the saved snapshots and the environment they were recorded in
+    History,
+    /// Show how the tests performed across the saved snapshots, with the
+    /// points where they changed and slow drifts
+    Trend {
+        /// Only show the test with this name
+        endpoint: Option<String>,
+
+        /// How many of the most recent snapshots to look at
+        #[arg(long, default_value_t = 100)]
+        last: usize,
+    },
+    /// Remove the snapshots the retention policy in ballast.json doesn't keep
+    Prune {
+        /// Only show what would be pruned
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Manage the saved snapshots
+    Snapshot {
+        #[command(subcommand)]
+        command: SnapshotCommand,
+    },
+    /// Generate a report from the saved snapshots
+    Report {
+        #[command(subcommand)]
+        report: ReportCommand,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum SnapshotCommand {
+    /// Upgrade every saved snapshot to the schema of this version of ballast
+    Migrate,
+    /// Keep a snapshot regardless of the retention policy
+    Pin {
+        /// The id of the snapshot, or the start of it
+        id: String,
+    },
+    /// Let the retention policy prune a pinned snapshot again
+    Unpin {
+        /// The id of the snapshot, or the start of it
+        id: String,
+    },
+    /// Print a snapshot as json, to import it on another machine
+    Export {
+        /// The id of the snapshot, or the start of it
+        id: String,
+    },
+    /// Add the snapshots of an exported snapshot or another store's json file
+    Import {
+        file: String,
+
+        /// What to do with a snapshot that has the id of one already saved
+        #[arg(long, value_enum, default_value_t = OnConflict::Skip)]
+        on_conflict: OnConflict,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum OnConflict {
+    /// Keep the saved snapshot
+    Skip,
+    /// Replace the saved snapshot with the imported one
+    Replace,
+    /// Keep both, the imported snapshot gets a new id
+    Rename,
+}
+
+#[derive(Subcommand)]
+pub enum ReportCommand {
+    /// Generate a self contained html report with latency charts of the latest snapshot
+    Html {
+        /// Where to write the html file
+        #[arg(long, default_value = "./ballast-report.html")]
+        output: String,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum ImportSource {
+    /// Import every operation of an OpenAPI 3 specification (json or yaml)
+    Openapi {
+        spec: String,
+
+        /// Base url of the api, defaults to the first server of the specification
+        #[arg(long)]
+        base_url: Option<String>,
+
+        /// Validate responses against the documented response schemas
+        #[arg(long)]
+        validate: bool,
+    },
+    /// Import the requests of a HAR capture
+    Har {
+        file: String,
+
+        /// Group all requests into a single test with this name, run in order
+        #[arg(long)]
+        scenario: Option<String>,
+    },
+    /// Import a curl command, given directly or as a file (- reads from stdin)
+    Curl { command: String },
+    /// Import the requests of a Postman v2 collection
+    Postman { collection: String },
 }
@@ -1,27 +1,183 @@
 use crate::config::{Config, EndpointConfig};
 use crate::printer::Printer;
-use crate::process::Test;
+use crate::process::{allowed_deviation, Test};
 use crate::snapshot::Snapshot;
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReasonKind {
+    Threshold,
+    Status,
+    Body,
+    Headers,
+    Schema,
+}
+
+impl ReasonKind {
+    pub fn title(&self) -> &'static str {
+        match self {
+            ReasonKind::Threshold => "Threshold",
+            _ => "Expected",
+        }
+    }
+}
+
+impl std::fmt::Display for ReasonKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let kind = match self {
+            ReasonKind::Threshold => "threshold",
+            ReasonKind::Status => "status",
+            ReasonKind::Body => "body",
+            ReasonKind::Headers => "headers",
+            ReasonKind::Schema => "schema",
+        };
+        write!(f, "{}", kind)
+    }
+}
+
+/// Why a test failed, as printed below a failing test.
+#[derive(Debug, Clone)]
+pub struct Reason {
+    pub kind: ReasonKind,
+    pub message: String,
+}
+
+/// A stat of a test next to the same stat of its baseline.
+#[derive(Debug, Clone)]
+pub struct Metric {
+    pub name: &'static str,
+    pub label: &'static str,
+    pub unit: &'static str,
+    pub current: f64,
+    pub baseline: Option<f64>,
+}
+
+impl Metric {
+    pub fn delta(&self) -> Option<f64> {
+        self.baseline.map(|baseline| self.current - baseline)
+    }
+
+    pub fn percent_change(&self) -> Option<f64> {
+        match self.baseline {
+            Some(baseline) if baseline != 0.0 => Some((self.current - baseline) / baseline * 100.0),
+            _ => None,
+        }
+    }
+}
+
+pub fn metrics(test: &Test, baseline: Option<&Test>) -> Vec<Metric> {
+    vec![
+        Metric {
+            name: "average_response_time",
+            label: "Avg response time",
+            unit: "ms",
+            current: test.stats.average_response_time,
+            baseline: baseline.map(|b| b.stats.average_response_time),
+        },
+        Metric {
+            name: "max_response_time",
+            label: "Max response time",
+            unit: "ms",
+            current: test.stats.max_response_time,
+            baseline: baseline.map(|b| b.stats.max_response_time),
+        },
+        Metric {
+            name: "min_response_time",
+            label: "Min response time",
+            unit: "ms",
+            current: test.stats.min_response_time,
+            baseline: baseline.map(|b| b.stats.min_response_time),
+        },
+    ]
+}
+
+/// The test of the same endpoint in the snapshot that is compared against.
+pub fn find_baseline<'a>(test: &Test, latest: Option<&'a Snapshot>) -> Option<&'a Test> {
+    latest.and_then(|snapshot| {
+        snapshot
+            .tests
+            .iter()
+            .find(|t| t.config.endpoint_name == test.config.endpoint_name)
+    })
+}
+
+pub fn find_endpoint<'a>(test: &Test, config: &'a Config) -> &'a EndpointConfig {
+    config
+        .endpoints
+        .iter()
+        .find(|e| e.name == test.config.endpoint_name)
+        .unwrap()
+}
+
+pub fn failure_reasons(
+    test: &Test,
+    endpoint_config: &EndpointConfig,
+    baseline: Option<&Test>,
+) -> Vec<Reason> {
+    let mut reasons = vec![];
+
+    if !test.within_threshold {
+        if let Some(baseline) = baseline {
+            reasons.push(Reason {
+                kind: ReasonKind::Threshold,
+                message: format!(
+                    "average response time {}ms (expected {}ms +/- {}ms)",
+                    test.stats.average_response_time,
+                    baseline.stats.average_response_time,
+                    (allowed_deviation(endpoint_config, baseline) * 10.0).round() / 10.0
+                ),
+            });
+        }
+    }
+
+    if test.expected.status_code == Some(false) {
+        reasons.push(Reason {
+            kind: ReasonKind::Status,
+            message: format!(
+                "expected status code {}",
+                endpoint_config.expected_status.unwrap()
+            ),
+        });
+    }
+
+    if test.expected.body == Some(false) {
+        reasons.push(Reason {
+            kind: ReasonKind::Body,
+            message: format!(
+                "expected body {}",
+                endpoint_config.expected_body.as_ref().unwrap()
+            ),
+        });
+    }
+
+    if test.expected.headers == Some(false) {
+        reasons.push(Reason {
+            kind: ReasonKind::Headers,
+            message: format!(
+                "expected headers {:?}",
+                endpoint_config.expected_headers.as_ref().unwrap()
+            ),
+        });
+    }
+
+    if test.expected.schema == Some(false) {
+        reasons.push(Reason {
+            kind: ReasonKind::Schema,
+            message: "expected response body matching the response schema".to_string(),
+        });
+    }
+
+    reasons
+}
+
 pub fn compare_tests(
-    tests: &Vec<Test>,
+    tests: &[Test],
     config: &Config,
     latest: Option<&Snapshot>,
     printer: &Printer,
 ) {
     for test in tests {
-        let other = match latest {
-            Some(snapshot) => snapshot
-                .tests
-                .iter()
-                .find(|t| t.config.endpoint_name == test.config.endpoint_name),
-            None => None,
-        };
-        let endpoint_config: &EndpointConfig = config
-            .endpoints
-            .iter()
-            .find(|e| e.name == test.config.endpoint_name)
-            .unwrap();
+        let other = find_baseline(test, latest);
+        let endpoint_config = find_endpoint(test, config);
         match test.success {
             true => {
                 printer.blank_line().print_with_green(
@@ -31,109 +187,31 @@ pub fn compare_tests(
                 );
             }
             false => {
-                let mut reasons_for_failure: Vec<String> = vec![];
                 printer.blank_line().print_with_red(
                     "FAIL",
                     &format!("{} {}", test.config.endpoint_name, test.config.endpoint_url),
                     0,
                 );
 
-                if !test.within_threshold {
-                    match other {
-                        Some(other) => {
-                            let expected = other.stats.average_response_time;
-                            let actual = test.stats.average_response_time;
-                            printer.print_with_yellow(
-                                "Threshold",
-                                &format!(
-                                    "average response time {}ms (expected {}ms +/- {}ms)",
-                                    actual,
-                                    expected,
-                                    endpoint_config.threshold.unwrap()
-                                ),
-                                4,
-                            );
-                        }
-                        None => {}
-                    }
-                };
-
-                if test.expected.status_code == Some(false) {
-                    reasons_for_failure.push(format!(
-                        "expected status code {}",
-                        endpoint_config.expected_status.unwrap()
-                    ));
-                    printer.print_with_yellow(
-                        "Expected",
-                        &format!(
-                            "expected status code {}",
-                            endpoint_config.expected_status.unwrap(),
-                        ),
-                        4,
-                    );
-                }
-
-                if test.expected.body == Some(false) {
-                    reasons_for_failure.push(format!(
-                        "expected body {}",
-                        endpoint_config.expected_body.as_ref().unwrap()
-                    ));
-                    printer.print_with_yellow(
-                        "Expected",
-                        &format!(
-                            "expected body {:?}",
-                            endpoint_config.expected_body.as_ref().unwrap(),
-                        ),
-                        4,
-                    );
-                }
-
-                if test.expected.headers == Some(false) {
-                    printer.print_with_yellow(
-                        "Expected",
-                        &format!(
-                            "expected headers {:?}",
-                            endpoint_config.expected_headers.as_ref().unwrap(),
-                        ),
-                        4,
-                    );
+                for reason in failure_reasons(test, endpoint_config, other) {
+                    printer.print_with_yellow(reason.kind.title(), &reason.message, 4);
                 }
             }
         };
 
-        printer
-            .print_stat(
-                "Avg response time",
-                test.stats.average_response_time,
-                match other {
-                    Some(other) => {
-                        Some(test.stats.average_response_time - other.stats.average_response_time)
-                    }
-                    None => None,
-                },
-                "ms",
-            )
-            .print_stat(
-                "Max response time",
-                test.stats.max_response_time,
-                match other {
-                    Some(other) => {
-                        Some(test.stats.max_response_time - other.stats.max_response_time)
-                    }
-                    None => None,
-                },
-                "ms",
-            )
-            .print_stat(
-                "Min response time",
-                test.stats.min_response_time,
-                match other {
-                    Some(other) => {
-                        Some(test.stats.min_response_time - other.stats.min_response_time)
-                    }
-                    None => None,
-                },
-                "ms",
+        for metric in metrics(test, other) {
+            printer.print_stat(metric.label, metric.current, metric.delta(), metric.unit);
+        }
+
+        if let Some(noise) = &test.noise {
+            printer.print_plain(
+                &format!(
+                    "run to run noise {:.1}ms over {} runs",
+                    noise.std_dev,
+                    noise.averages.len()
+                ),
+                4,
             );
+        }
     }
 }
@@ -1,27 +1,39 @@
 use anyhow::Result;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::collections::HashMap;
 use std::fs::read_to_string;
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct Config {
     pub endpoints: Vec<EndpointConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub variables: Option<HashMap<String, String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sinks: Option<Vec<SinkConfig>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub baseline: Option<BaselineStrategy>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub storage: Option<StorageConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retention: Option<RetentionConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub server: Option<ServerConfig>,
 }
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub enum Method {
-    #[serde(alias = "GET")]
+    #[serde(rename = "GET", alias = "Get")]
     Get,
-    #[serde(alias = "POST")]
+    #[serde(rename = "POST", alias = "Post")]
     Post,
-    #[serde(alias = "PUT")]
+    #[serde(rename = "PUT", alias = "Put")]
     Put,
-    #[serde(alias = "DELETE")]
+    #[serde(rename = "DELETE", alias = "Delete")]
     Delete,
-    #[serde(alias = "PATCH")]
+    #[serde(rename = "PATCH", alias = "Patch")]
     Patch,
-    #[serde(alias = "OPTIONS")]
+    #[serde(rename = "OPTIONS", alias = "Options")]
     Options,
 }
 
@@ -39,20 +51,248 @@ impl std::fmt::Display for Method {
     }
 }
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct EndpointConfig {
     pub name: String,
     pub url: String,
     pub method: Method,
     pub concurrent_requests: u64,
     pub cycles: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub headers: Option<HashMap<String, String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub body: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub expected_status: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub expected_body: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub expected_headers: Option<HashMap<String, String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response_schema: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub threshold: Option<u128>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub threshold_mode: Option<ThresholdMode>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub noise_factor: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub ramp: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<DataConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub steps: Option<Vec<StepConfig>>,
+}
+
+/// How far the average response time of a test may rise above its baseline's.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ThresholdMode {
+    /// By `threshold` milliseconds.
+    Fixed,
+    /// By `noise_factor` times the standard deviation between the repeated
+    /// runs of the baseline.
+    Noise,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct StepConfig {
+    pub url: String,
+    pub method: Method,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub headers: Option<HashMap<String, String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub body: Option<Value>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "lowercase")]
+pub enum DataFormat {
+    Csv,
+    #[serde(alias = "ndjson")]
+    Jsonl,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "lowercase")]
+pub enum DataOrder {
+    Sequential,
+    Random,
+    Unique,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "lowercase")]
+pub enum OnExhausted {
+    Recycle,
+    Stop,
+    Error,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DataConfig {
+    pub path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub format: Option<DataFormat>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub order: Option<DataOrder>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub on_exhausted: Option<OnExhausted>,
+}
+
+fn default_main_branch() -> String {
+    "main".to_string()
+}
+
+/// Which saved snapshot a run is compared against.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(tag = "strategy", rename_all = "snake_case")]
+pub enum BaselineStrategy {
+    /// The most recent snapshot.
+    #[default]
+    Latest,
+    /// The most recent snapshot of the current git branch.
+    Branch,
+    /// The most recent snapshot of the commit the current branch forked off
+    /// `branch`, or of the closest commit before it.
+    MergeBase {
+        #[serde(default = "default_main_branch")]
+        branch: String,
+    },
+    /// The most recent snapshot of a commit, tag or branch.
+    Ref {
+        #[serde(rename = "ref")]
+        reference: String,
+    },
+}
+
+impl std::fmt::Display for BaselineStrategy {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            BaselineStrategy::Latest => write!(f, "the latest snapshot"),
+            BaselineStrategy::Branch => write!(f, "the current branch"),
+            BaselineStrategy::MergeBase { branch } => write!(f, "the merge base with {}", branch),
+            BaselineStrategy::Ref { reference } => write!(f, "{}", reference),
+        }
+    }
+}
+
+/// Where the snapshots are saved.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum StorageConfig {
+    /// Every snapshot in a single json file.
+    Json {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        path: Option<String>,
+    },
+    /// A json file per snapshot in a directory.
+    Directory {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        path: Option<String>,
+    },
+    /// A sqlite database, indexed by endpoint, branch, commit and time.
+    Sqlite {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        path: Option<String>,
+    },
+}
+
+/// Which snapshots are kept when the history is pruned. A snapshot is kept
+/// when any of the rules keeps it, pinned snapshots are always kept.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct RetentionConfig {
+    /// The most recent snapshots.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keep_last: Option<usize>,
+    /// Snapshots younger than this many days.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keep_days: Option<u64>,
+    /// The most recent snapshot of each of the last weeks with snapshots.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keep_weekly: Option<usize>,
+    /// The most recent snapshot of each of the last months with snapshots.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keep_monthly: Option<usize>,
+    /// Drop the samples of the snapshots older than this many days, keeping
+    /// their stats.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub compact_after_days: Option<u64>,
+}
+
+/// The local service under test, started before the load and stopped after.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ServerConfig {
+    /// Run with `sh -c`, e.g. `cargo run --release`.
+    pub command: String,
+    /// Run to completion before the server is started.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub build: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dir: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub env: Option<HashMap<String, String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ready: Option<ReadyCheck>,
+    /// Seconds to wait for the server to be ready.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ready_timeout: Option<u64>,
+    /// A file the output of the server is written to, it's discarded without.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub log: Option<String>,
+}
+
+/// How to tell the server is ready for the load.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum ReadyCheck {
+    /// The url answers with any response.
+    Http { url: String },
+    /// The port accepts connections.
+    Tcp {
+        port: u16,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        host: Option<String>,
+    },
+}
+
+impl std::fmt::Display for ReadyCheck {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ReadyCheck::Http { url } => write!(f, "{}", url),
+            ReadyCheck::Tcp { port, host } => {
+                write!(f, "{}:{}", host.as_deref().unwrap_or("127.0.0.1"), port)
+            }
+        }
+    }
+}
+
+/// Where the results of a run are sent besides the snapshot.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum SinkConfig {
+    /// Line protocol posted to an influxdb write url every `interval` seconds.
+    Influxdb {
+        url: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        headers: Option<HashMap<String, String>>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        interval: Option<u64>,
+    },
+    /// A timing and counters for every request, sent over udp.
+    Statsd {
+        addr: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        prefix: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        dogstatsd: Option<bool>,
+    },
+    /// The json report of the run posted once it finished.
+    Webhook {
+        url: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        headers: Option<HashMap<String, String>>,
+    },
 }
 
 impl Config {
@@ -61,4 +301,10 @@ impl Config {
         let config: Config = serde_json::from_str(&contents)?;
         Ok(config)
     }
+
+    pub fn write_config_file(&self, path: &str) -> Result<()> {
+        let contents = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, contents)?;
+        Ok(())
+    }
 }
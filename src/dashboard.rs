@@ -0,0 +1,207 @@
+use crate::printer::Printer;
+use crate::process::percentile;
+use crate::request::{Request, RequestOutput};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+
+// Rolling rps and percentiles are computed over the requests that finished
+// within this window.
+const WINDOW: Duration = Duration::from_secs(5);
+const TTY_INTERVAL: Duration = Duration::from_millis(250);
+const PLAIN_INTERVAL: Duration = Duration::from_secs(5);
+const SPARKLINE_WIDTH: usize = 40;
+const SPARKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// Counters of the requests of a single endpoint's load, updated by the
+/// requests themselves while they run.
+pub struct LiveStats {
+    started: Instant,
+    total: u64,
+    expected_status: Option<u16>,
+    in_flight: AtomicU64,
+    completed: AtomicU64,
+    errors: AtomicU64,
+    recent: Mutex<VecDeque<(Instant, u128)>>,
+}
+
+impl LiveStats {
+    pub fn new(total: u64, expected_status: Option<u16>) -> Arc<Self> {
+        Arc::new(Self {
+            started: Instant::now(),
+            total,
+            expected_status,
+            in_flight: AtomicU64::new(0),
+            completed: AtomicU64::new(0),
+            errors: AtomicU64::new(0),
+            recent: Mutex::new(VecDeque::new()),
+        })
+    }
+
+    /// Wraps a request so it's counted as in flight while it runs.
+    pub fn track(self: &Arc<Self>, request: Request) -> Request {
+        let stats = self.clone();
+        Box::pin(async move {
+            stats.in_flight.fetch_add(1, Ordering::Relaxed);
+            let output = request.await;
+            stats.finish(&output);
+            output
+        })
+    }
+
+    fn finish(&self, output: &RequestOutput) {
+        self.in_flight.fetch_sub(1, Ordering::Relaxed);
+        self.completed.fetch_add(1, Ordering::Relaxed);
+
+        if output.error_kind(self.expected_status).is_some() {
+            self.errors.fetch_add(1, Ordering::Relaxed);
+        }
+
+        let now = Instant::now();
+        let mut recent = self.recent.lock().unwrap();
+        recent.push_back((now, output.duration));
+        while recent
+            .front()
+            .is_some_and(|(finished, _)| now.duration_since(*finished) > WINDOW)
+        {
+            recent.pop_front();
+        }
+    }
+
+    fn snapshot(&self) -> LiveSnapshot {
+        let now = Instant::now();
+        let recent = self.recent.lock().unwrap();
+        let mut durations = recent
+            .iter()
+            .filter(|(finished, _)| now.duration_since(*finished) <= WINDOW)
+            .map(|(_, duration)| *duration as f64)
+            .collect::<Vec<_>>();
+        durations.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+        let window = now.duration_since(self.started).min(WINDOW).as_secs_f64();
+        LiveSnapshot {
+            completed: self.completed.load(Ordering::Relaxed),
+            in_flight: self.in_flight.load(Ordering::Relaxed),
+            errors: self.errors.load(Ordering::Relaxed),
+            rps: match window > 0.0 {
+                true => durations.len() as f64 / window,
+                false => 0.0,
+            },
+            p50: percentile(&durations, 50.0),
+            p99: percentile(&durations, 99.0),
+        }
+    }
+}
+
+struct LiveSnapshot {
+    completed: u64,
+    in_flight: u64,
+    errors: u64,
+    rps: f64,
+    p50: f64,
+    p99: f64,
+}
+
+fn progress_bar(done: u64, total: u64, width: usize) -> String {
+    let filled = match total {
+        0 => width,
+        total => ((done as f64 / total as f64) * width as f64).round() as usize,
+    }
+    .min(width);
+    format!("[{}{}]", "#".repeat(filled), "-".repeat(width - filled))
+}
+
+pub fn sparkline(values: &[f64]) -> String {
+    let max = values.iter().cloned().fold(0.0, f64::max);
+    values
+        .iter()
+        .map(|v| match max > 0.0 {
+            true => SPARKS[((v / max) * (SPARKS.len() - 1) as f64).round() as usize],
+            false => SPARKS[0],
+        })
+        .collect()
+}
+
+/// Redraws the live stats of an endpoint's load until it's stopped. When the
+/// output isn't a terminal a plain progress line is printed every few seconds
+/// instead.
+pub struct Dashboard {
+    stop: oneshot::Sender<()>,
+    handle: JoinHandle<()>,
+}
+
+impl Dashboard {
+    pub fn start(printer: Printer, name: String, stats: Arc<LiveStats>) -> Self {
+        let (stop, mut stopped) = oneshot::channel::<()>();
+        let is_term = printer.is_term();
+
+        let handle = tokio::spawn(async move {
+            let mut interval = tokio::time::interval(match is_term {
+                true => TTY_INTERVAL,
+                false => PLAIN_INTERVAL,
+            });
+            // The first tick completes right away, there's nothing to show yet.
+            interval.tick().await;
+            let mut p50_history: VecDeque<f64> = VecDeque::new();
+            let mut drawn = 0;
+
+            loop {
+                tokio::select! {
+                    _ = &mut stopped => break,
+                    _ = interval.tick() => {}
+                }
+
+                let live = stats.snapshot();
+                let summary = format!(
+                    "{}/{} requests, {:.1} rps, {} in flight, p50 {}ms, p99 {}ms, {} errors",
+                    live.completed,
+                    stats.total,
+                    live.rps,
+                    live.in_flight,
+                    live.p50,
+                    live.p99,
+                    live.errors
+                );
+
+                match is_term {
+                    true => {
+                        p50_history.push_back(live.p50);
+                        if p50_history.len() > SPARKLINE_WIDTH {
+                            p50_history.pop_front();
+                        }
+                        printer
+                            .clear_lines(drawn)
+                            .print_plain(
+                                &format!(
+                                    "{} {}",
+                                    progress_bar(live.completed, stats.total, 20),
+                                    summary
+                                ),
+                                6,
+                            )
+                            .print_plain(
+                                &format!("p50 {}", sparkline(p50_history.make_contiguous())),
+                                6,
+                            );
+                        drawn = 2;
+                    }
+                    false => {
+                        printer.print_with_yellow("Progress", &format!("{} {}", name, summary), 4);
+                    }
+                }
+            }
+
+            printer.clear_lines(drawn);
+        });
+
+        Self { stop, handle }
+    }
+
+    pub async fn stop(self) {
+        let _ = self.stop.send(());
+        let _ = self.handle.await;
+    }
+}
@@ -0,0 +1,133 @@
+use crate::config::Config;
+use serde::{Deserialize, Serialize};
+use std::process::Command;
+
+/// The commit the run was made on.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct GitInfo {
+    pub commit: String,
+    pub branch: String,
+    /// Whether there were uncommitted changes.
+    pub dirty: bool,
+}
+
+/// Where and with what a snapshot was recorded, to tell whether two
+/// snapshots can be compared.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Environment {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub git: Option<GitInfo>,
+    pub hostname: String,
+    pub cpu_model: String,
+    pub cpu_count: usize,
+    pub os: String,
+    pub ballast_version: String,
+    /// Hash of the tests and variables of the config the run was made with.
+    pub config_hash: String,
+}
+
+fn run(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    match output.status.success() {
+        true => Some(String::from_utf8_lossy(&output.stdout).trim().to_string()),
+        false => None,
+    }
+}
+
+/// Runs git in the current directory, `None` if it fails.
+pub fn git(args: &[&str]) -> Option<String> {
+    run("git", args)
+}
+
+pub fn git_info() -> Option<GitInfo> {
+    Some(GitInfo {
+        commit: git(&["rev-parse", "HEAD"])?,
+        branch: git(&["rev-parse", "--abbrev-ref", "HEAD"])?,
+        dirty: !git(&["status", "--porcelain"])?.is_empty(),
+    })
+}
+
+fn hostname() -> String {
+    std::fs::read_to_string("/proc/sys/kernel/hostname")
+        .ok()
+        .map(|h| h.trim().to_string())
+        .or_else(|| run("hostname", &[]))
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+fn cpu_model() -> String {
+    std::fs::read_to_string("/proc/cpuinfo")
+        .ok()
+        .and_then(|info| {
+            info.lines()
+                .find(|line| line.starts_with("model name"))
+                .and_then(|line| line.split_once(':'))
+                .map(|(_, model)| model.trim().to_string())
+        })
+        .or_else(|| run("sysctl", &["-n", "machdep.cpu.brand_string"]))
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+// FNV-1a, the hash has to stay the same across builds of ballast.
+pub fn hash(input: &[u8]) -> String {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in input {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    format!("{:016x}", hash)
+}
+
+impl Environment {
+    pub fn capture(config: &Config) -> Self {
+        Self {
+            git: git_info(),
+            hostname: hostname(),
+            cpu_model: cpu_model(),
+            cpu_count: std::thread::available_parallelism()
+                .map(|n| n.get())
+                .unwrap_or(1),
+            os: format!("{} {}", std::env::consts::OS, std::env::consts::ARCH),
+            ballast_version: env!("CARGO_PKG_VERSION").to_string(),
+            // Only what changes the load is hashed, not where the results go.
+            config_hash: hash(
+                serde_json::to_string(&(&config.endpoints, &config.variables))
+                    .unwrap_or_default()
+                    .as_bytes(),
+            ),
+        }
+    }
+
+    /// What differs between this environment and the one of the baseline, in
+    /// a way that makes their response times hard to compare. The commit is
+    /// expected to change between runs, so it isn't included.
+    pub fn differences(&self, baseline: &Environment) -> Vec<String> {
+        let mut differences = vec![];
+        let mut compare = |name: &str, current: String, baseline: String| {
+            if current != baseline {
+                differences.push(format!("{} is {} (baseline {})", name, current, baseline));
+            }
+        };
+
+        compare("host", self.hostname.clone(), baseline.hostname.clone());
+        compare("cpu", self.cpu_model.clone(), baseline.cpu_model.clone());
+        compare(
+            "cpu count",
+            self.cpu_count.to_string(),
+            baseline.cpu_count.to_string(),
+        );
+        compare("os", self.os.clone(), baseline.os.clone());
+        compare(
+            "ballast version",
+            self.ballast_version.clone(),
+            baseline.ballast_version.clone(),
+        );
+        compare(
+            "config hash",
+            self.config_hash.clone(),
+            baseline.config_hash.clone(),
+        );
+
+        differences
+    }
+}
@@ -0,0 +1,143 @@
+use crate::config::{DataConfig, DataFormat, DataOrder, OnExhausted};
+use anyhow::{anyhow, Context, Result};
+use rand::Rng;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+pub type Row = HashMap<String, String>;
+
+/// Hands out rows of a csv or json lines file to the requests of an endpoint.
+///
+/// With the `unique` order every virtual user (the position of a request within
+/// a cycle) reads from its own slice of the rows, so no two virtual users ever
+/// send the same row.
+pub struct Feeder {
+    rows: Vec<Row>,
+    order: DataOrder,
+    on_exhausted: OnExhausted,
+    cursors: Vec<AtomicUsize>,
+}
+
+impl Feeder {
+    pub fn from_config(config: &DataConfig, virtual_users: u64) -> Result<Self> {
+        let format = match &config.format {
+            Some(format) => format.clone(),
+            None => match config.path.rsplit('.').next() {
+                Some("csv") => DataFormat::Csv,
+                Some("jsonl") | Some("ndjson") => DataFormat::Jsonl,
+                _ => {
+                    return Err(anyhow!(
+                        "Unable to infer the data format of {}, set \"format\" to csv or jsonl",
+                        config.path
+                    ))
+                }
+            },
+        };
+
+        let contents = std::fs::read_to_string(&config.path)
+            .with_context(|| format!("Failed to read data file {}", config.path))?;
+        let rows = match format {
+            DataFormat::Csv => parse_csv(&contents),
+            DataFormat::Jsonl => parse_jsonl(&contents),
+        }
+        .with_context(|| format!("Failed to parse data file {}", config.path))?;
+
+        if rows.is_empty() {
+            return Err(anyhow!("Data file {} has no rows", config.path));
+        }
+
+        let order = config.order.clone().unwrap_or(DataOrder::Sequential);
+        let num_cursors = match order {
+            DataOrder::Unique => virtual_users.max(1) as usize,
+            _ => 1,
+        };
+
+        Ok(Self {
+            rows,
+            order,
+            on_exhausted: config.on_exhausted.clone().unwrap_or(OnExhausted::Recycle),
+            cursors: (0..num_cursors).map(|_| AtomicUsize::new(0)).collect(),
+        })
+    }
+
+    /// The row for the next request of a virtual user, `None` once the data is
+    /// exhausted and the endpoint is configured to stop.
+    pub fn next(&self, virtual_user: u64) -> Result<Option<&Row>> {
+        let (index, stride, offset) = match self.order {
+            DataOrder::Random => return Ok(Some(self.sample())),
+            DataOrder::Sequential => (self.cursors[0].fetch_add(1, Ordering::Relaxed), 1, 0),
+            DataOrder::Unique => {
+                let offset = virtual_user as usize % self.cursors.len();
+                let index = self.cursors[offset].fetch_add(1, Ordering::Relaxed);
+                (index, self.cursors.len(), offset)
+            }
+        };
+
+        // Number of rows that belong to this virtual user.
+        let available = (self.rows.len() + stride - 1 - offset) / stride;
+        if available == 0 {
+            return Err(anyhow!(
+                "Not enough data rows for {} unique virtual users",
+                self.cursors.len()
+            ));
+        }
+
+        let index = match (index < available, &self.on_exhausted) {
+            (true, _) | (false, OnExhausted::Recycle) => index % available,
+            (false, OnExhausted::Stop) => return Ok(None),
+            (false, OnExhausted::Error) => {
+                return Err(anyhow!("Data exhausted after {} rows", self.rows.len()))
+            }
+        };
+
+        Ok(Some(&self.rows[index * stride + offset]))
+    }
+
+    /// A random row, without advancing any of the cursors.
+    pub fn sample(&self) -> &Row {
+        &self.rows[rand::thread_rng().gen_range(0..self.rows.len())]
+    }
+}
+
+fn parse_csv(contents: &str) -> Result<Vec<Row>> {
+    let mut reader = csv::Reader::from_reader(contents.as_bytes());
+    let headers = reader.headers()?.clone();
+
+    reader
+        .records()
+        .map(|record| {
+            let record = record?;
+            Ok(headers
+                .iter()
+                .zip(record.iter())
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect())
+        })
+        .collect()
+}
+
+fn parse_jsonl(contents: &str) -> Result<Vec<Row>> {
+    contents
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            let value: Value = serde_json::from_str(line)
+                .with_context(|| format!("Invalid json on line {}", i + 1))?;
+            let object = value
+                .as_object()
+                .ok_or_else(|| anyhow!("Line {} is not a json object", i + 1))?;
+            Ok(object
+                .iter()
+                .map(|(k, v)| {
+                    let v = match v {
+                        Value::String(s) => s.clone(),
+                        other => other.to_string(),
+                    };
+                    (k.clone(), v)
+                })
+                .collect())
+        })
+        .collect()
+}
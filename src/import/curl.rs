@@ -0,0 +1,192 @@
+use super::{parse_body, parse_method, replayable_headers, ImportOptions};
+use crate::config::{Config, Method};
+use anyhow::{anyhow, Result};
+use base64::Engine;
+
+/// Generates an endpoint from a curl command, as copied from browser dev tools
+/// or a bug report.
+pub fn import(command: &str, options: &ImportOptions) -> Result<Config> {
+    let args = split_args(command)?;
+    let mut args = args.into_iter().peekable();
+    if args.peek().map(|a| a.as_str()) == Some("curl") {
+        args.next();
+    }
+
+    let mut url = None;
+    let mut method = None;
+    let mut headers = vec![];
+    let mut data: Vec<String> = vec![];
+    let mut data_in_query = false;
+
+    while let Some(arg) = args.next() {
+        let mut value = |flag: &str| {
+            args.next()
+                .ok_or_else(|| anyhow!("Missing value for curl option {}", flag))
+        };
+
+        match arg.as_str() {
+            "-X" | "--request" => method = Some(value(&arg)?),
+            "-H" | "--header" => {
+                let header = value(&arg)?;
+                if let Some((name, value)) = header.split_once(':') {
+                    headers.push((name.trim().to_string(), value.trim().to_string()));
+                }
+            }
+            "-d" | "--data" | "--data-raw" | "--data-binary" | "--data-ascii"
+            | "--data-urlencode" => data.push(value(&arg)?),
+            "--json" => {
+                data.push(value(&arg)?);
+                headers.push(("Content-Type".to_string(), "application/json".to_string()));
+                headers.push(("Accept".to_string(), "application/json".to_string()));
+            }
+            "-u" | "--user" => {
+                let credentials = base64::engine::general_purpose::STANDARD.encode(value(&arg)?);
+                headers.push((
+                    "Authorization".to_string(),
+                    format!("Basic {}", credentials),
+                ));
+            }
+            "-A" | "--user-agent" => headers.push(("User-Agent".to_string(), value(&arg)?)),
+            "-b" | "--cookie" => headers.push(("Cookie".to_string(), value(&arg)?)),
+            "-e" | "--referer" => headers.push(("Referer".to_string(), value(&arg)?)),
+            "-G" | "--get" => data_in_query = true,
+            "--url" => url = Some(value(&arg)?),
+            // Options that take a value but don't change the request itself.
+            "-o" | "--output" | "-m" | "--max-time" | "--connect-timeout" | "-w"
+            | "--write-out" | "--retry" => {
+                value(&arg)?;
+            }
+            other if other.starts_with('-') => {}
+            other => url = Some(other.to_string()),
+        }
+    }
+
+    let mut url = url.ok_or_else(|| anyhow!("No url found in curl command"))?;
+    let data = data.join("&");
+
+    let body = match (data.is_empty(), data_in_query) {
+        (true, _) => None,
+        (false, true) => {
+            let separator = match url.contains('?') {
+                true => '&',
+                false => '?',
+            };
+            url = format!("{}{}{}", url, separator, data);
+            None
+        }
+        (false, false) => Some(parse_body(&data)),
+    };
+
+    let method = match method {
+        Some(method) => {
+            parse_method(&method).ok_or_else(|| anyhow!("Unsupported HTTP method {}", method))?
+        }
+        None if body.is_some() => Method::Post,
+        None => Method::Get,
+    };
+
+    let path = url
+        .split("://")
+        .nth(1)
+        .and_then(|u| u.find('/').map(|i| u[i..].to_string()))
+        .unwrap_or_else(|| "/".to_string());
+    let name = format!("{} {}", method, path.split('?').next().unwrap_or("/"));
+
+    Ok(Config {
+        endpoints: vec![super::endpoint(
+            name,
+            method,
+            url,
+            replayable_headers(headers),
+            body,
+            options,
+        )],
+        ..Default::default()
+    })
+}
+
+/// Splits a command line into arguments the way a posix shell would for the
+/// quoting styles curl commands are usually copied with.
+fn split_args(command: &str) -> Result<Vec<String>> {
+    let mut args = vec![];
+    let mut current = String::new();
+    let mut has_arg = false;
+    let mut chars = command.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' => {
+                has_arg = true;
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => current.push(c),
+                        None => return Err(anyhow!("Unterminated ' quote in curl command")),
+                    }
+                }
+            }
+            '"' => {
+                has_arg = true;
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c @ ('"' | '\\' | '$' | '`')) => current.push(c),
+                            Some('\n') => {}
+                            Some(c) => {
+                                current.push('\\');
+                                current.push(c);
+                            }
+                            None => return Err(anyhow!("Unterminated \" quote in curl command")),
+                        },
+                        Some(c) => current.push(c),
+                        None => return Err(anyhow!("Unterminated \" quote in curl command")),
+                    }
+                }
+            }
+            // Bash's $'...' quoting, which chrome uses for bodies with special characters.
+            '$' if chars.peek() == Some(&'\'') => {
+                chars.next();
+                has_arg = true;
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some('\\') => match chars.next() {
+                            Some('n') => current.push('\n'),
+                            Some('t') => current.push('\t'),
+                            Some('r') => current.push('\r'),
+                            Some(c) => current.push(c),
+                            None => return Err(anyhow!("Unterminated $' quote in curl command")),
+                        },
+                        Some(c) => current.push(c),
+                        None => return Err(anyhow!("Unterminated $' quote in curl command")),
+                    }
+                }
+            }
+            '\\' => match chars.next() {
+                Some('\n') | Some('\r') => {}
+                Some(c) => {
+                    has_arg = true;
+                    current.push(c);
+                }
+                None => {}
+            },
+            c if c.is_whitespace() => {
+                if has_arg {
+                    args.push(std::mem::take(&mut current));
+                    has_arg = false;
+                }
+            }
+            c => {
+                has_arg = true;
+                current.push(c);
+            }
+        }
+    }
+
+    if has_arg {
+        args.push(current);
+    }
+
+    Ok(args)
+}
@@ -0,0 +1,107 @@
+use super::{parse_body, parse_method, replayable_headers, unique_name, ImportOptions};
+use crate::config::{Config, StepConfig};
+use anyhow::{anyhow, Context, Result};
+use serde_json::Value;
+
+/// Generates an endpoint for every http request of a HAR capture, or a single
+/// endpoint running all of them in order when `scenario` names one.
+pub fn import(contents: &str, scenario: Option<String>, options: &ImportOptions) -> Result<Config> {
+    let har: Value = serde_json::from_str(contents).context("Failed to parse HAR file")?;
+    let entries = har
+        .pointer("/log/entries")
+        .and_then(|e| e.as_array())
+        .ok_or_else(|| anyhow!("HAR file has no log entries"))?;
+
+    let mut endpoints = vec![];
+    for entry in entries {
+        let request = match entry.get("request") {
+            Some(request) => request,
+            None => continue,
+        };
+        let url = request
+            .get("url")
+            .and_then(|u| u.as_str())
+            .unwrap_or_default();
+        // Skips data urls, websockets and such that can't be replayed.
+        if !url.starts_with("http://") && !url.starts_with("https://") {
+            continue;
+        }
+        let method = match request
+            .get("method")
+            .and_then(|m| m.as_str())
+            .and_then(parse_method)
+        {
+            Some(method) => method,
+            None => continue,
+        };
+
+        let headers = replayable_headers(
+            request
+                .get("headers")
+                .and_then(|h| h.as_array())
+                .into_iter()
+                .flatten()
+                .filter_map(|h| {
+                    Some((
+                        h.get("name")?.as_str()?.to_string(),
+                        h.get("value")?.as_str()?.to_string(),
+                    ))
+                }),
+        );
+        let body = request
+            .pointer("/postData/text")
+            .and_then(|t| t.as_str())
+            .filter(|t| !t.is_empty())
+            .map(parse_body);
+
+        let path = url
+            .split("://")
+            .nth(1)
+            .and_then(|u| u.find('/').map(|i| &u[i..]));
+        let name = unique_name(&endpoints, format!("{} {}", method, path.unwrap_or("/")));
+
+        let mut endpoint = super::endpoint(name, method, url.to_string(), headers, body, options);
+        endpoint.expected_status = entry
+            .pointer("/response/status")
+            .and_then(|s| s.as_u64())
+            .filter(|s| *s > 0)
+            .map(|s| s as u16);
+        endpoints.push(endpoint);
+    }
+
+    if endpoints.is_empty() {
+        return Err(anyhow!(
+            "HAR file has no http requests that can be imported"
+        ));
+    }
+
+    let endpoints = match scenario {
+        Some(name) => {
+            let mut endpoints = endpoints.into_iter();
+            let mut first = endpoints.next().unwrap();
+            let mut expected_status = first.expected_status;
+            let steps = endpoints
+                .map(|e| {
+                    expected_status = e.expected_status;
+                    StepConfig {
+                        url: e.url,
+                        method: e.method,
+                        headers: e.headers,
+                        body: e.body,
+                    }
+                })
+                .collect::<Vec<_>>();
+            first.name = name;
+            // A scenario reports the response of its last step.
+            first.expected_status = expected_status;
+            first.steps = (!steps.is_empty()).then_some(steps);
+            vec![first]
+        }
+        None => endpoints,
+    };
+
+    Ok(Config {
+        endpoints,
+        ..Default::default()
+    })
+}
@@ -0,0 +1,144 @@
+pub mod curl;
+pub mod har;
+pub mod openapi;
+pub mod postman;
+
+use crate::config::{Config, EndpointConfig, Method};
+use anyhow::{Context, Result};
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Settings shared by every importer for the endpoints it generates.
+pub struct ImportOptions {
+    pub base_url: Option<String>,
+    pub concurrent_requests: u64,
+    pub cycles: u64,
+    pub validate: bool,
+}
+
+// Headers that only apply to a single connection, or that reqwest sets on
+// its own, and shouldn't be replayed from a captured request.
+const SKIPPED_HEADERS: [&str; 12] = [
+    "connection",
+    "keep-alive",
+    "proxy-authenticate",
+    "proxy-authorization",
+    "proxy-connection",
+    "te",
+    "trailer",
+    "transfer-encoding",
+    "upgrade",
+    "host",
+    "content-length",
+    "accept-encoding",
+];
+
+/// Adds the imported endpoints and variables to the config file at `path`,
+/// creating it if it doesn't exist yet. Endpoints with a name that is already in
+/// the config are skipped, returns the number of endpoints that were added and
+/// skipped.
+pub fn merge_into_config(path: &str, imported: Config) -> Result<(usize, usize)> {
+    let mut config = match std::fs::metadata(path) {
+        Ok(_) => Config::from_config_file(path)
+            .with_context(|| format!("Failed to read {} config file", path))?,
+        Err(_) => Config::default(),
+    };
+
+    let total = imported.endpoints.len();
+    let mut added = 0;
+    for endpoint in imported.endpoints {
+        if config.endpoints.iter().any(|e| e.name == endpoint.name) {
+            continue;
+        }
+        config.endpoints.push(endpoint);
+        added += 1;
+    }
+
+    if let Some(imported_variables) = imported.variables {
+        let variables = config.variables.get_or_insert_with(HashMap::new);
+        for (name, value) in imported_variables {
+            variables.entry(name).or_insert(value);
+        }
+    }
+
+    config
+        .write_config_file(path)
+        .with_context(|| format!("Failed to write {} config file", path))?;
+
+    Ok((added, total - added))
+}
+
+pub fn parse_method(method: &str) -> Option<Method> {
+    match method.to_uppercase().as_str() {
+        "GET" => Some(Method::Get),
+        "POST" => Some(Method::Post),
+        "PUT" => Some(Method::Put),
+        "DELETE" => Some(Method::Delete),
+        "PATCH" => Some(Method::Patch),
+        _ => None,
+    }
+}
+
+/// Drops hop-by-hop and HTTP/2 pseudo headers, returns `None` when nothing is
+/// left.
+pub fn replayable_headers(
+    headers: impl IntoIterator<Item = (String, String)>,
+) -> Option<HashMap<String, String>> {
+    let headers: HashMap<String, String> = headers
+        .into_iter()
+        .filter(|(name, _)| {
+            !name.starts_with(':') && !SKIPPED_HEADERS.contains(&name.to_lowercase().as_str())
+        })
+        .collect();
+
+    match headers.is_empty() {
+        true => None,
+        false => Some(headers),
+    }
+}
+
+/// Json bodies are kept as json, anything else is sent as the raw text.
+pub fn parse_body(text: &str) -> Value {
+    serde_json::from_str(text).unwrap_or_else(|_| Value::String(text.to_string()))
+}
+
+/// Appends a counter to `name` if an endpoint with that name was already
+/// imported, endpoints are matched by name so they have to be unique.
+pub fn unique_name(endpoints: &[EndpointConfig], name: String) -> String {
+    let mut candidate = name.clone();
+    let mut count = 1;
+    while endpoints.iter().any(|e| e.name == candidate) {
+        count += 1;
+        candidate = format!("{} ({})", name, count);
+    }
+    candidate
+}
+
+pub fn endpoint(
+    name: String,
+    method: Method,
+    url: String,
+    headers: Option<HashMap<String, String>>,
+    body: Option<Value>,
+    options: &ImportOptions,
+) -> EndpointConfig {
+    EndpointConfig {
+        name,
+        url,
+        method,
+        concurrent_requests: options.concurrent_requests,
+        cycles: options.cycles,
+        headers,
+        body,
+        expected_status: None,
+        expected_body: None,
+        expected_headers: None,
+        response_schema: None,
+        threshold: None,
+        threshold_mode: None,
+        noise_factor: None,
+        ramp: None,
+        data: None,
+        steps: None,
+    }
+}
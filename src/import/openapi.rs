@@ -0,0 +1,303 @@
+use super::ImportOptions;
+use crate::config::{Config, Method};
+use anyhow::{anyhow, Result};
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+
+// Bounds how many `$ref`s deep references and schema examples are followed,
+// recursive schemas would otherwise never finish.
+const MAX_DEPTH: usize = 8;
+
+/// Generates an endpoint for every operation of an OpenAPI 3 specification in
+/// either json or yaml.
+pub fn import(contents: &str, options: &ImportOptions) -> Result<Config> {
+    let spec: Value = match serde_json::from_str(contents) {
+        Ok(spec) => spec,
+        Err(_) => serde_yaml::from_str(contents)
+            .map_err(|e| anyhow!("Failed to parse OpenAPI specification: {}", e))?,
+    };
+
+    if spec.get("openapi").is_none() {
+        return Err(anyhow!(
+            "Only OpenAPI 3 specifications are supported, missing \"openapi\" version field"
+        ));
+    }
+
+    let base_url = match &options.base_url {
+        Some(base_url) => base_url.clone(),
+        None => server_url(&spec).unwrap_or_else(|| "http://localhost:8080".to_string()),
+    };
+    let base_url = base_url.trim_end_matches('/');
+
+    let paths = spec
+        .get("paths")
+        .and_then(|p| p.as_object())
+        .ok_or_else(|| anyhow!("OpenAPI specification has no paths"))?;
+
+    let mut endpoints = vec![];
+    for (path, item) in paths {
+        let item = resolve(&spec, item, 0);
+        let shared_parameters = item.get("parameters").cloned().unwrap_or(json!([]));
+
+        for (key, method) in [
+            ("get", Method::Get),
+            ("post", Method::Post),
+            ("put", Method::Put),
+            ("delete", Method::Delete),
+            ("patch", Method::Patch),
+        ] {
+            let operation = match item.get(key) {
+                Some(operation) => operation,
+                None => continue,
+            };
+
+            let mut parameters = shared_parameters.as_array().cloned().unwrap_or_default();
+            if let Some(Value::Array(own)) = operation.get("parameters") {
+                parameters.extend(own.iter().cloned());
+            }
+
+            let name = match operation.get("operationId").and_then(|o| o.as_str()) {
+                Some(id) => id.to_string(),
+                None => format!("{} {}", method, path),
+            };
+
+            let body = operation
+                .get("requestBody")
+                .and_then(json_content)
+                .and_then(media_example);
+
+            let (expected_status, response_schema) = success_response(operation);
+
+            let mut endpoint = super::endpoint(
+                name,
+                method,
+                format!("{}{}", base_url, example_path(path, &parameters)),
+                body.as_ref().map(|_| {
+                    HashMap::from([("Content-Type".to_string(), "application/json".to_string())])
+                }),
+                body,
+                options,
+            );
+            endpoint.expected_status = expected_status;
+            if options.validate {
+                endpoint.response_schema = response_schema;
+            }
+            endpoints.push(endpoint);
+        }
+    }
+
+    Ok(Config {
+        endpoints,
+        ..Default::default()
+    })
+}
+
+/// Replaces every `$ref` in `value` with the part of the specification it
+/// points to.
+fn resolve(spec: &Value, value: &Value, depth: usize) -> Value {
+    if depth > MAX_DEPTH {
+        return json!({});
+    }
+
+    match value {
+        Value::Object(map) => {
+            if let Some(Value::String(reference)) = map.get("$ref") {
+                return match reference.strip_prefix('#').and_then(|p| spec.pointer(p)) {
+                    Some(target) => resolve(spec, target, depth + 1),
+                    None => json!({}),
+                };
+            }
+            Value::Object(
+                map.iter()
+                    .map(|(k, v)| (k.clone(), resolve(spec, v, depth)))
+                    .collect(),
+            )
+        }
+        Value::Array(values) => {
+            Value::Array(values.iter().map(|v| resolve(spec, v, depth)).collect())
+        }
+        other => other.clone(),
+    }
+}
+
+fn server_url(spec: &Value) -> Option<String> {
+    let server = spec.get("servers")?.get(0)?;
+    let mut url = server.get("url")?.as_str()?.to_string();
+    if let Some(Value::Object(variables)) = server.get("variables") {
+        for (name, variable) in variables {
+            if let Some(default) = variable.get("default").and_then(|d| d.as_str()) {
+                url = url.replace(&format!("{{{}}}", name), default);
+            }
+        }
+    }
+    Some(url)
+}
+
+/// The path with path parameters and required query parameters filled in with
+/// example values.
+fn example_path(path: &str, parameters: &[Value]) -> String {
+    let mut path = path.to_string();
+    let mut query = vec![];
+
+    for parameter in parameters {
+        let name = match parameter.get("name").and_then(|n| n.as_str()) {
+            Some(name) => name,
+            None => continue,
+        };
+        let example = parameter.get("example").cloned().unwrap_or_else(|| {
+            example_for_schema(parameter.get("schema").unwrap_or(&json!({})), 0)
+        });
+        let example = match example {
+            Value::String(s) => s,
+            other => other.to_string(),
+        };
+
+        match parameter.get("in").and_then(|i| i.as_str()) {
+            Some("path") => path = path.replace(&format!("{{{}}}", name), &example),
+            Some("query") if parameter.get("required") == Some(&Value::Bool(true)) => {
+                query.push(format!("{}={}", name, example))
+            }
+            _ => {}
+        }
+    }
+
+    match query.is_empty() {
+        true => path,
+        false => format!("{}?{}", path, query.join("&")),
+    }
+}
+
+fn json_content(object: &Value) -> Option<&Value> {
+    let content = object.get("content")?.as_object()?;
+    content.get("application/json").or_else(|| {
+        content
+            .iter()
+            .find(|(k, _)| k.contains("json"))
+            .map(|(_, v)| v)
+    })
+}
+
+fn media_example(media: &Value) -> Option<Value> {
+    if let Some(example) = media.get("example") {
+        return Some(example.clone());
+    }
+    if let Some(example) = media
+        .get("examples")
+        .and_then(|e| e.as_object())
+        .and_then(|e| e.values().next())
+        .and_then(|e| e.get("value"))
+    {
+        return Some(example.clone());
+    }
+    media
+        .get("schema")
+        .map(|schema| example_for_schema(schema, 0))
+}
+
+/// The lowest documented 2xx status of an operation and the schema of its
+/// json response.
+fn success_response(operation: &Value) -> (Option<u16>, Option<Value>) {
+    let responses = match operation.get("responses").and_then(|r| r.as_object()) {
+        Some(responses) => responses,
+        None => return (None, None),
+    };
+
+    let success = responses
+        .iter()
+        .filter_map(|(code, response)| Some((code.parse::<u16>().ok()?, response)))
+        .filter(|(code, _)| (200..300).contains(code))
+        .min_by_key(|(code, _)| *code);
+
+    match success {
+        Some((code, response)) => (
+            Some(code),
+            json_content(response)
+                .and_then(|c| c.get("schema"))
+                .cloned(),
+        ),
+        None => (None, None),
+    }
+}
+
+fn example_for_schema(schema: &Value, depth: usize) -> Value {
+    if depth > MAX_DEPTH {
+        return Value::Null;
+    }
+
+    for key in ["example", "default"] {
+        if let Some(example) = schema.get(key) {
+            return example.clone();
+        }
+    }
+    if let Some(first) = schema.get("enum").and_then(|e| e.get(0)) {
+        return first.clone();
+    }
+    for key in ["allOf", "oneOf", "anyOf"] {
+        if let Some(Value::Array(schemas)) = schema.get(key) {
+            return match key {
+                // Every schema of an allOf applies, so their examples are merged.
+                "allOf" => {
+                    let mut merged = Map::new();
+                    for schema in schemas {
+                        if let Value::Object(part) = example_for_schema(schema, depth + 1) {
+                            merged.extend(part);
+                        }
+                    }
+                    Value::Object(merged)
+                }
+                _ => schemas
+                    .first()
+                    .map(|s| example_for_schema(s, depth + 1))
+                    .unwrap_or(Value::Null),
+            };
+        }
+    }
+
+    let schema_type = schema.get("type").and_then(|t| t.as_str());
+    let schema_type = match (schema_type, schema.get("properties")) {
+        (None, Some(_)) => Some("object"),
+        (schema_type, _) => schema_type,
+    };
+
+    match schema_type {
+        Some("object") => {
+            let required = schema.get("required").and_then(|r| r.as_array());
+            let is_required =
+                |key: &str| required.is_some_and(|r| r.iter().any(|k| k.as_str() == Some(key)));
+            // Optional properties of nested objects are left out, which keeps
+            // examples of recursive schemas small.
+            Value::Object(
+                schema
+                    .get("properties")
+                    .and_then(|p| p.as_object())
+                    .map(|properties| {
+                        properties
+                            .iter()
+                            .filter(|(k, _)| depth < 2 || is_required(k))
+                            .map(|(k, v)| (k.clone(), example_for_schema(v, depth + 1)))
+                            .collect()
+                    })
+                    .unwrap_or_default(),
+            )
+        }
+        Some("array") => json!([example_for_schema(
+            schema.get("items").unwrap_or(&json!({})),
+            depth + 1
+        )]),
+        Some("integer") => json!(schema.get("minimum").and_then(|m| m.as_i64()).unwrap_or(1)),
+        Some("number") => json!(schema
+            .get("minimum")
+            .and_then(|m| m.as_f64())
+            .unwrap_or(1.0)),
+        Some("boolean") => json!(true),
+        Some("string") => match schema.get("format").and_then(|f| f.as_str()) {
+            Some("date-time") => json!("2024-01-01T00:00:00Z"),
+            Some("date") => json!("2024-01-01"),
+            Some("uuid") => json!("00000000-0000-0000-0000-000000000000"),
+            Some("email") => json!("user@example.com"),
+            Some("uri") => json!("https://example.com"),
+            _ => json!("string"),
+        },
+        _ => Value::Null,
+    }
+}
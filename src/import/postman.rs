@@ -0,0 +1,168 @@
+use super::{parse_body, parse_method, replayable_headers, unique_name, ImportOptions};
+use crate::config::{Config, EndpointConfig};
+use anyhow::{anyhow, Context, Result};
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Generates an endpoint for every request of a Postman v2 collection, requests
+/// in folders are named after their folder path.
+///
+/// Postman's `{{variable}}` references are rewritten to ballast's `${variable}`
+/// and the collection's variables are added to the config's variables.
+pub fn import(contents: &str, options: &ImportOptions) -> Result<Config> {
+    let collection: Value =
+        serde_json::from_str(contents).context("Failed to parse Postman collection")?;
+    let items = collection
+        .get("item")
+        .and_then(|i| i.as_array())
+        .ok_or_else(|| anyhow!("Postman collection has no items"))?;
+
+    let mut endpoints = vec![];
+    collect_items(items, "", options, &mut endpoints);
+
+    let variables: HashMap<String, String> = collection
+        .get("variable")
+        .and_then(|v| v.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|v| {
+            let value = match v.get("value")? {
+                Value::String(s) => s.clone(),
+                other => other.to_string(),
+            };
+            Some((
+                v.get("key")?.as_str()?.to_string(),
+                convert_variables(&value),
+            ))
+        })
+        .collect();
+
+    Ok(Config {
+        endpoints,
+        variables: (!variables.is_empty()).then_some(variables),
+        ..Default::default()
+    })
+}
+
+fn collect_items(
+    items: &[Value],
+    folder: &str,
+    options: &ImportOptions,
+    endpoints: &mut Vec<EndpointConfig>,
+) {
+    for item in items {
+        let name = item
+            .get("name")
+            .and_then(|n| n.as_str())
+            .unwrap_or("Request");
+        let name = match folder.is_empty() {
+            true => name.to_string(),
+            false => format!("{} / {}", folder, name),
+        };
+
+        if let Some(Value::Array(children)) = item.get("item") {
+            collect_items(children, &name, options, endpoints);
+            continue;
+        }
+
+        let request = match item.get("request") {
+            Some(request) => request,
+            None => continue,
+        };
+
+        // A request can be just its url.
+        let (method, url) = match request {
+            Value::String(url) => (Some("GET"), Some(url.as_str())),
+            request => (
+                request
+                    .get("method")
+                    .and_then(|m| m.as_str())
+                    .or(Some("GET")),
+                match request.get("url") {
+                    Some(Value::String(url)) => Some(url.as_str()),
+                    Some(url) => url.get("raw").and_then(|r| r.as_str()),
+                    None => None,
+                },
+            ),
+        };
+        let (method, url) = match (method.and_then(parse_method), url) {
+            (Some(method), Some(url)) => (method, convert_variables(url)),
+            _ => continue,
+        };
+
+        let headers = replayable_headers(
+            request
+                .get("header")
+                .and_then(|h| h.as_array())
+                .into_iter()
+                .flatten()
+                .filter(|h| h.get("disabled") != Some(&Value::Bool(true)))
+                .filter_map(|h| {
+                    Some((
+                        h.get("key")?.as_str()?.to_string(),
+                        convert_variables(h.get("value")?.as_str()?),
+                    ))
+                }),
+        );
+
+        let body =
+            request
+                .get("body")
+                .and_then(|body| match body.get("mode").and_then(|m| m.as_str()) {
+                    Some("raw") => body
+                        .get("raw")
+                        .and_then(|r| r.as_str())
+                        .filter(|r| !r.is_empty())
+                        .map(|r| parse_body(&convert_variables(r))),
+                    Some("urlencoded") => {
+                        let fields = body
+                            .get("urlencoded")?
+                            .as_array()?
+                            .iter()
+                            .filter(|f| f.get("disabled") != Some(&Value::Bool(true)))
+                            .filter_map(|f| {
+                                Some(format!(
+                                    "{}={}",
+                                    f.get("key")?.as_str()?,
+                                    f.get("value")?.as_str()?
+                                ))
+                            })
+                            .collect::<Vec<_>>();
+                        Some(Value::String(convert_variables(&fields.join("&"))))
+                    }
+                    _ => None,
+                });
+
+        let name = unique_name(endpoints, name);
+        endpoints.push(super::endpoint(name, method, url, headers, body, options));
+    }
+}
+
+/// Rewrites postman's `{{name}}` variables to `${name}`, postman's dynamic
+/// variables map to the matching generators where there is one.
+fn convert_variables(input: &str) -> String {
+    let mut output = String::with_capacity(input.len());
+    let mut rest = input;
+
+    while let Some(start) = rest.find("{{") {
+        let end = match rest[start..].find("}}") {
+            Some(end) => start + end,
+            None => break,
+        };
+        output.push_str(&rest[..start]);
+        let name = rest[start + 2..end].trim();
+        let replacement = match name {
+            "$guid" | "$randomUUID" => "{{uuid}}".to_string(),
+            "$timestamp" => "{{now_unix}}".to_string(),
+            "$isoTimestamp" => "{{now_iso}}".to_string(),
+            "$randomInt" => "{{random_int 0 1000}}".to_string(),
+            "$randomEmail" | "$randomExampleEmail" => "{{random_email}}".to_string(),
+            name => format!("${{{}}}", name),
+        };
+        output.push_str(&replacement);
+        rest = &rest[end + 2..];
+    }
+
+    output.push_str(rest);
+    output
+}
@@ -1,34 +1,483 @@
+mod bisect;
+mod cli;
 mod compare;
 mod config;
+mod dashboard;
+mod environment;
+mod feeder;
+mod import;
+mod metrics;
 mod printer;
 mod process;
+mod report;
 mod request;
+mod retention;
 mod runner;
+mod samples;
+mod schema;
+mod server;
+mod sink;
 mod snapshot;
+mod store;
+mod template;
+mod trend;
 use anyhow::Context;
 use anyhow::Result;
 use clap::Parser;
+use cli::{Args, Command, ImportSource, OnConflict, OutputFormat, ReportCommand, SnapshotCommand};
 use compare::compare_tests;
-use config::Config;
+use config::{BaselineStrategy, Config, ServerConfig};
 use console::Term;
+use environment::Environment;
+use metrics::Metrics;
 use printer::Printer;
 use process::process;
-use runner::Runner;
+use runner::{Loads, Runner};
+use server::Server;
+use sink::{Finished, Sinks};
 use snapshot::Snapshot;
 use std::fs;
-use tokio;
-
-#[derive(Parser)]
-struct Args {
-    #[arg(long = "no-snapshot", required = false)]
-    no_snapshot: bool,
-}
+use store::SnapshotStore;
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    let term = Term::stdout();
+    let args = Args::parse();
+    // Keeps stdout clean for the results when they are printed as json.
+    let term = match (args.format, &args.command) {
+        (OutputFormat::Json, _)
+        | (
+            _,
+            Some(Command::Snapshot {
+                command: SnapshotCommand::Export { .. },
+            }),
+        ) => Term::stderr(),
+        _ => Term::stdout(),
+    };
     let printer = Printer::new(term);
 
+    match args.command {
+        Some(Command::Import {
+            source,
+            output,
+            concurrent_requests,
+            cycles,
+        }) => import(source, &output, concurrent_requests, cycles, &printer),
+        Some(Command::Bisect {
+            good,
+            bad,
+            build,
+            start,
+            ready,
+            ready_timeout,
+            endpoints,
+        }) => {
+            let config = Config::from_config_file("./ballast.json")
+                .context("Failed to read ./ballast.json config file")?;
+            let options = bisect::BisectOptions {
+                good,
+                bad,
+                build,
+                start,
+                ready,
+                ready_timeout,
+                endpoints,
+            };
+            bisect::bisect(options, config, &printer).await
+        }
+        Some(Command::History) => history(&printer),
+        Some(Command::Trend { endpoint, last }) => {
+            let store = open_store()?;
+            let result = trend::trend(store.as_ref(), endpoint, last, &printer);
+            print_store_warnings(store.as_ref(), &printer);
+            result
+        }
+        Some(Command::Prune { dry_run }) => prune(dry_run, &printer),
+        Some(Command::Snapshot { command }) => match command {
+            SnapshotCommand::Migrate => migrate(&printer),
+            SnapshotCommand::Pin { id } => pin(&id, true, &printer),
+            SnapshotCommand::Unpin { id } => pin(&id, false, &printer),
+            SnapshotCommand::Export { id } => export_snapshot(&id, &printer),
+            SnapshotCommand::Import { file, on_conflict } => {
+                import_snapshots(&file, on_conflict, &printer)
+            }
+        },
+        Some(Command::Report { report }) => write_snapshot_report(report, &printer),
+        None => run(&args, &printer).await,
+    }
+}
+
+/// ./ballast.json, or the default config when there is none.
+fn config_or_default() -> Result<Config> {
+    match fs::metadata("./ballast.json") {
+        Ok(_) => Config::from_config_file("./ballast.json")
+            .context("Failed to read ./ballast.json config file"),
+        Err(_) => Ok(Config::default()),
+    }
+}
+
+/// The snapshot store of ./ballast.json, or the default one without a config.
+fn open_store() -> Result<Box<dyn SnapshotStore>> {
+    store::open(&config_or_default()?)
+}
+
+fn print_store_warnings(store: &dyn SnapshotStore, printer: &Printer) {
+    for warning in store.take_warnings() {
+        printer.print_with_yellow("Warning", &warning, 0);
+    }
+}
+
+fn history(printer: &Printer) -> Result<()> {
+    let store = open_store()?;
+    let snapshots = store.read()?;
+    print_store_warnings(store.as_ref(), printer);
+    if snapshots.is_empty() {
+        printer.print_with_red("ERROR", "No snapshots found", 0);
+        return Ok(());
+    }
+
+    for snapshot in snapshots.iter().rev() {
+        let passed = snapshot.tests.iter().filter(|t| t.success).count();
+        let title = snapshot.title();
+        let timestamp = report::format_timestamp(snapshot.timestamp);
+        match passed == snapshot.tests.len() {
+            true => printer.print_with_green(&timestamp, &title, 0),
+            false => printer.print_with_red(&timestamp, &title, 0),
+        };
+        printer.print_plain(
+            &format!(
+                "{}, {} of {} tests passed{}",
+                snapshot.id,
+                passed,
+                snapshot.tests.len(),
+                match snapshot.pinned {
+                    true => ", pinned",
+                    false => "",
+                }
+            ),
+            4,
+        );
+        if let Some(environment) = &snapshot.environment {
+            printer.print_plain(
+                &format!(
+                    "{}, {} ({} cpus), {}, ballast {}, config {}",
+                    environment.hostname,
+                    environment.cpu_model,
+                    environment.cpu_count,
+                    environment.os,
+                    environment.ballast_version,
+                    environment.config_hash
+                ),
+                4,
+            );
+        }
+    }
+
+    Ok(())
+}
+
+fn print_pruned(pruned: &retention::Pruned, printer: &Printer) {
+    if pruned.removed + pruned.compacted > 0 {
+        printer.print_with_green(
+            "Pruned",
+            &format!(
+                "{} snapshots, dropped the samples of {} more",
+                pruned.removed, pruned.compacted
+            ),
+            0,
+        );
+    }
+}
+
+fn prune(dry_run: bool, printer: &Printer) -> Result<()> {
+    let config = config_or_default()?;
+    let retention = match &config.retention {
+        Some(retention) => retention,
+        None => {
+            printer.print_with_red("ERROR", "No retention policy in ballast.json", 0);
+            return Ok(());
+        }
+    };
+
+    let store = store::open(&config)?;
+    let now = chrono::Utc::now().timestamp() as u64;
+    let pruned = retention::prune(store.as_ref(), retention, now, dry_run)?;
+    print_store_warnings(store.as_ref(), printer);
+    match (dry_run, pruned.removed + pruned.compacted) {
+        (_, 0) => printer.print_with_green("Done", "nothing to prune", 0),
+        (true, _) => printer.print_with_yellow(
+            "Would prune",
+            &format!(
+                "{} snapshots, drop the samples of {} more",
+                pruned.removed, pruned.compacted
+            ),
+            0,
+        ),
+        (false, _) => {
+            print_pruned(&pruned, printer);
+            printer
+        }
+    };
+
+    Ok(())
+}
+
+/// Pins or unpins the snapshot with an id starting with `id`.
+fn pin(id: &str, pinned: bool, printer: &Printer) -> Result<()> {
+    let store = open_store()?;
+    let id = Snapshot::find(store.as_ref(), id)?.id;
+    print_store_warnings(store.as_ref(), printer);
+
+    store.update(&mut |mut snapshots| {
+        snapshots
+            .iter_mut()
+            .filter(|s| s.id == id)
+            .for_each(|s| s.pinned = pinned);
+        snapshots
+    })?;
+    printer.print_with_green(
+        match pinned {
+            true => "Pinned",
+            false => "Unpinned",
+        },
+        &format!("snapshot {}", id),
+        0,
+    );
+
+    Ok(())
+}
+
+fn export_snapshot(id: &str, printer: &Printer) -> Result<()> {
+    let store = open_store()?;
+    let snapshot = Snapshot::find(store.as_ref(), id)?;
+    print_store_warnings(store.as_ref(), printer);
+    println!("{}", serde_json::to_string_pretty(&snapshot)?);
+
+    Ok(())
+}
+
+fn import_snapshots(file: &str, on_conflict: OnConflict, printer: &Printer) -> Result<()> {
+    let imported = Snapshot::read_file(file)?;
+    let store = open_store()?;
+
+    let (mut added, mut replaced, mut skipped) = (0, 0, 0);
+    store.update(&mut |mut snapshots| {
+        for snapshot in &imported {
+            match snapshots.iter().position(|s| s.id == snapshot.id) {
+                None => {
+                    snapshots.push(snapshot.clone());
+                    added += 1;
+                }
+                Some(index) => match on_conflict {
+                    OnConflict::Skip => skipped += 1,
+                    OnConflict::Replace => {
+                        snapshots[index] = snapshot.clone();
+                        replaced += 1;
+                    }
+                    OnConflict::Rename => {
+                        let mut snapshot = snapshot.clone();
+                        snapshot.id = Snapshot::new_id();
+                        snapshots.push(snapshot);
+                        added += 1;
+                    }
+                },
+            }
+        }
+        snapshots.sort_by_key(|s| s.timestamp);
+        snapshots
+    })?;
+    print_store_warnings(store.as_ref(), printer);
+
+    printer.print_with_green(
+        "Imported",
+        &format!(
+            "{} snapshots from {} into {}",
+            added + replaced,
+            file,
+            store.location()
+        ),
+        0,
+    );
+    if replaced > 0 {
+        printer.print_with_yellow(
+            "Replaced",
+            &format!("{} snapshots with the same id", replaced),
+            0,
+        );
+    }
+    if skipped > 0 {
+        printer.print_with_yellow(
+            "Skipped",
+            &format!(
+                "{} snapshots with ids already in {}",
+                skipped,
+                store.location()
+            ),
+            0,
+        );
+    }
+
+    Ok(())
+}
+
+fn migrate(printer: &Printer) -> Result<()> {
+    let store = open_store()?;
+    // Snapshots are upgraded as they are read, rewriting them saves that.
+    let mut migrated = 0;
+    store.update(&mut |snapshots| {
+        migrated = snapshots.len();
+        snapshots
+    })?;
+    print_store_warnings(store.as_ref(), printer);
+    printer.print_with_green(
+        "Migrated",
+        &format!(
+            "{} snapshots in {} to schema version {}",
+            migrated,
+            store.location(),
+            store::migrate::SCHEMA_VERSION
+        ),
+        0,
+    );
+
+    Ok(())
+}
+
+fn write_snapshot_report(report: ReportCommand, printer: &Printer) -> Result<()> {
+    let store = open_store()?;
+    let snapshots = store.read()?;
+    print_store_warnings(store.as_ref(), printer);
+
+    let (current, baseline) = match snapshots.as_slice() {
+        [] => {
+            printer.print_with_red("ERROR", "No snapshots found to report on", 0);
+            return Ok(());
+        }
+        [.., baseline, current] => (current, Some(baseline)),
+        [.., current] => (current, None),
+    };
+
+    match report {
+        ReportCommand::Html { output } => {
+            let html = report::html::render(current, baseline, &snapshots);
+            fs::write(&output, html)
+                .with_context(|| format!("Failed to write html report to {}", output))?;
+            printer.print_with_green("Saved", &format!("html report to {}", output), 0);
+        }
+    }
+
+    Ok(())
+}
+
+fn import(
+    source: ImportSource,
+    output: &str,
+    concurrent_requests: u64,
+    cycles: u64,
+    printer: &Printer,
+) -> Result<()> {
+    let mut options = import::ImportOptions {
+        base_url: None,
+        concurrent_requests,
+        cycles,
+        validate: false,
+    };
+    let read =
+        |path: &str| fs::read_to_string(path).with_context(|| format!("Failed to read {}", path));
+
+    let (path, imported) = match source {
+        ImportSource::Openapi {
+            spec,
+            base_url,
+            validate,
+        } => {
+            options.base_url = base_url;
+            options.validate = validate;
+            let imported = import::openapi::import(&read(&spec)?, &options)?;
+            (spec, imported)
+        }
+        ImportSource::Har { file, scenario } => {
+            let imported = import::har::import(&read(&file)?, scenario, &options)?;
+            (file, imported)
+        }
+        ImportSource::Curl { command } => {
+            let contents = match command.as_str() {
+                "-" => std::io::read_to_string(std::io::stdin())?,
+                path if fs::metadata(path).is_ok() => read(path)?,
+                _ => command.clone(),
+            };
+            let imported = import::curl::import(&contents, &options)?;
+            ("curl command".to_string(), imported)
+        }
+        ImportSource::Postman { collection } => {
+            let imported = import::postman::import(&read(&collection)?, &options)?;
+            (collection, imported)
+        }
+    };
+
+    let (added, skipped) = import::merge_into_config(output, imported)?;
+    printer.print_with_green(
+        "Imported",
+        &format!("{} tests from {} into {}", added, path, output),
+        0,
+    );
+    if skipped > 0 {
+        printer.print_with_yellow(
+            "Skipped",
+            &format!("{} tests with names already in {}", skipped, output),
+            0,
+        );
+    }
+
+    Ok(())
+}
+
+/// Builds and starts the server of the config, and waits until it's ready.
+async fn start_server(config: &ServerConfig, printer: &Printer) -> Result<Server> {
+    if let Some(build) = &config.build {
+        printer.print_with_yellow("Building", build, 0);
+        server::build(config)?;
+        printer.clear_previous().print_with_green("Built", build, 0);
+    }
+
+    printer.print_with_yellow("Starting", &config.command, 0);
+    let mut server = Server::from_config(config)?;
+    match &config.ready {
+        Some(check) => {
+            let timeout = std::time::Duration::from_secs(config.ready_timeout.unwrap_or(30));
+            let ready = server.wait_for(check, timeout).await;
+            if let (Err(_), Some(log)) = (&ready, &config.log) {
+                printer.print_with_yellow("Warning", &format!("see {} for its output", log), 0);
+            }
+            ready?;
+        }
+        None => tokio::time::sleep(server::STARTUP_WAIT).await,
+    }
+    printer
+        .clear_previous()
+        .print_with_green("Started", &config.command, 0);
+
+    Ok(server)
+}
+
+/// Runs the load, against the server of the config when it has one, which is
+/// stopped once the load is done or failed.
+async fn load(runner: &Runner, server: Option<&ServerConfig>, printer: &Printer) -> Result<Loads> {
+    let mut server = match server {
+        Some(config) => Some(start_server(config, printer).await?),
+        None => None,
+    };
+    let results = runner.run(printer).await;
+    if let Some(server) = &mut server {
+        server.stop();
+        printer
+            .blank_line()
+            .print_with_green("Stopped", "the server", 0);
+    }
+
+    results
+}
+
+async fn run(args: &Args, printer: &Printer) -> Result<()> {
     match fs::metadata("./ballast.json") {
         Ok(metadata) => metadata,
         Err(_) => {
@@ -52,10 +501,68 @@ async fn main() -> Result<()> {
         ),
         0,
     );
-    let runner = Runner::new(config.clone());
-    let matches = Args::parse();
-    let results = runner.run(&printer).await?;
-    let latest_snapshot = Snapshot::latest()?;
+    let environment = Environment::capture(&config);
+    let strategy = match &args.against {
+        Some(reference) => BaselineStrategy::Ref {
+            reference: reference.clone(),
+        },
+        None => config.baseline.clone().unwrap_or_default(),
+    };
+    let store = store::open(&config)?;
+    let latest_snapshot = match &args.baseline_file {
+        // A whole store can be given as well, its latest snapshot is used.
+        Some(path) => match Snapshot::read_file(path)?.pop() {
+            Some(snapshot) => Some(snapshot),
+            None => return Err(anyhow::anyhow!("No snapshots in {}", path)),
+        },
+        None => Snapshot::latest(store.as_ref(), &strategy)?,
+    };
+    print_store_warnings(store.as_ref(), printer);
+    if latest_snapshot.is_none() && !matches!(strategy, BaselineStrategy::Latest) {
+        printer.print_with_yellow(
+            "Warning",
+            &format!("No snapshot found for {} to compare against", strategy),
+            0,
+        );
+    }
+    if let Some(baseline) = latest_snapshot
+        .as_ref()
+        .and_then(|s| s.environment.as_ref())
+    {
+        for difference in environment.differences(baseline) {
+            printer.print_with_yellow(
+                "Warning",
+                &format!("{}, results may not be comparable", difference),
+                0,
+            );
+        }
+    }
+    let mut runner = Runner::new(config.clone())?.with_repeat(args.repeat);
+    let metrics = match args.metrics_addr.is_some() || args.metrics_file.is_some() {
+        true => Some(Metrics::new(&config, args.desc.clone())),
+        false => None,
+    };
+    if let Some(metrics) = &metrics {
+        if let Some(addr) = args.metrics_addr {
+            metrics.serve(addr).await?;
+            printer.print_with_green("Serving", &format!("metrics on http://{}/metrics", addr), 0);
+        }
+        runner = runner.with_metrics(metrics.clone());
+    }
+    let sinks = Sinks::start(&config, args.desc.clone())?;
+    if let Some(sinks) = &sinks {
+        runner = runner.with_sinks(sinks.clone());
+    }
+    // The server runs in a process group of its own so the Ctrl-C of the
+    // terminal doesn't reach it, dropping the load stops it instead.
+    let results = tokio::select! {
+        results = load(&runner, config.server.as_ref(), printer) => results?,
+        _ = tokio::signal::ctrl_c() => return Err(anyhow::anyhow!("Interrupted")),
+    };
+    if let Some(target) = &args.samples {
+        let count = samples::write_samples(target, &results, &config)?;
+        printer.print_with_green("Saved", &format!("{} samples to {}", count, target.path), 0);
+    }
     printer.blank_line().print_with_yellow(
         "Processing",
         &format!("{} tests", config.endpoints.len()),
@@ -68,24 +575,63 @@ async fn main() -> Result<()> {
         0,
     );
 
-    compare_tests(
-        &processed_tests,
-        &config,
-        latest_snapshot.as_ref(),
-        &printer,
-    );
+    compare_tests(&processed_tests, &config, latest_snapshot.as_ref(), printer);
 
-    if !matches.no_snapshot {
-        Snapshot::new(processed_tests.clone()).unwrap().write()?;
+    let snapshot = Snapshot::new(processed_tests, args.desc.clone(), environment)?;
+    if let Some(sinks) = &sinks {
+        let finished = Finished {
+            snapshot: &snapshot,
+            config: &config,
+            latest: latest_snapshot.as_ref(),
+        };
+        for (name, error) in sinks.finish(&finished).await {
+            printer.print_with_yellow("Warning", &format!("{} sink failed: {:#}", name, error), 0);
+        }
+    }
+    if let (Some(metrics), Some(path)) = (&metrics, &args.metrics_file) {
+        metrics.write_file(path, &snapshot)?;
+        printer
+            .blank_line()
+            .print_with_green("Saved", &format!("metrics to {}", path), 0);
+    }
+    for target in &args.reports {
+        report::write_report(target, &snapshot, &config, latest_snapshot.as_ref())?;
+        printer.blank_line().print_with_green(
+            "Saved",
+            &format!("{} report to {}", target.format, target.path),
+            0,
+        );
+    }
+
+    if args.format == OutputFormat::Json {
+        println!(
+            "{}",
+            report::json::render(
+                &snapshot.tests,
+                &config,
+                latest_snapshot.as_ref(),
+                snapshot.timestamp
+            )?
+        );
+    }
+
+    if !args.no_snapshot {
+        store.write(&snapshot)?;
+        print_store_warnings(store.as_ref(), printer);
         printer.blank_line().print_with_green(
             "Saved",
             &format!(
                 "snapshot with {} tests to {}",
-                processed_tests.len(),
-                "./.ballast_snapshot.json"
+                snapshot.tests.len(),
+                store.location()
             ),
             0,
         );
+        if let Some(retention) = &config.retention {
+            let pruned = retention::prune(store.as_ref(), retention, snapshot.timestamp, false)?;
+            print_store_warnings(store.as_ref(), printer);
+            print_pruned(&pruned, printer);
+        }
     }
 
     Ok(())
@@ -0,0 +1,295 @@
+use crate::config::{Config, EndpointConfig};
+use crate::request::Request;
+use crate::snapshot::Snapshot;
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+// Upper bounds of the latency histogram buckets in seconds, the same as the
+// default buckets of the prometheus client libraries.
+const BUCKETS: [f64; 11] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+struct EndpointMetrics {
+    name: String,
+    method: String,
+    expected_status: Option<u16>,
+    in_flight: u64,
+    requests: BTreeMap<u16, u64>,
+    errors: BTreeMap<String, u64>,
+    bytes: u64,
+    // Non cumulative counts per bucket, the last one is +Inf.
+    buckets: [u64; BUCKETS.len() + 1],
+    sum: f64,
+    count: u64,
+}
+
+/// Counters and latency histograms of every endpoint of a run, rendered in
+/// the prometheus text exposition format.
+pub struct Metrics {
+    desc: Option<String>,
+    endpoints: Mutex<Vec<EndpointMetrics>>,
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+impl Metrics {
+    pub fn new(config: &Config, desc: Option<String>) -> Arc<Self> {
+        let endpoints = config
+            .endpoints
+            .iter()
+            .map(|endpoint| EndpointMetrics {
+                name: endpoint.name.clone(),
+                method: endpoint.method.to_string(),
+                expected_status: endpoint.expected_status,
+                in_flight: 0,
+                requests: BTreeMap::new(),
+                errors: BTreeMap::new(),
+                bytes: 0,
+                buckets: [0; BUCKETS.len() + 1],
+                sum: 0.0,
+                count: 0,
+            })
+            .collect();
+
+        Arc::new(Self {
+            desc,
+            endpoints: Mutex::new(endpoints),
+        })
+    }
+
+    fn update(&self, name: &str, f: impl FnOnce(&mut EndpointMetrics)) {
+        let mut endpoints = self.endpoints.lock().unwrap();
+        if let Some(endpoint) = endpoints.iter_mut().find(|e| e.name == name) {
+            f(endpoint);
+        }
+    }
+
+    /// Wraps a request of `endpoint` so it's recorded once it finishes.
+    pub fn track(self: &Arc<Self>, endpoint: &EndpointConfig, request: Request) -> Request {
+        let metrics = self.clone();
+        let name = endpoint.name.clone();
+        Box::pin(async move {
+            metrics.update(&name, |e| e.in_flight += 1);
+            let output = request.await;
+            metrics.update(&name, |e| {
+                e.in_flight -= 1;
+                *e.requests.entry(output.status).or_insert(0) += 1;
+                if let Some(error) = output.error_kind(e.expected_status) {
+                    *e.errors.entry(error.to_string()).or_insert(0) += 1;
+                }
+                e.bytes += output.bytes;
+
+                let seconds = output.duration as f64 / 1000.0;
+                let bucket = BUCKETS
+                    .iter()
+                    .position(|bound| seconds <= *bound)
+                    .unwrap_or(BUCKETS.len());
+                e.buckets[bucket] += 1;
+                e.sum += seconds;
+                e.count += 1;
+            });
+            output
+        })
+    }
+
+    fn labels(&self, endpoint: &str, method: &str, extra: &[(&str, &str)]) -> String {
+        let mut labels = vec![
+            format!("endpoint=\"{}\"", escape(endpoint)),
+            format!("method=\"{}\"", escape(method)),
+        ];
+        if let Some(desc) = &self.desc {
+            labels.push(format!("desc=\"{}\"", escape(desc)));
+        }
+        for (key, value) in extra {
+            labels.push(format!("{}=\"{}\"", key, escape(value)));
+        }
+        format!("{{{}}}", labels.join(","))
+    }
+
+    /// Renders the current state of every endpoint. With the snapshot of a
+    /// finished run the results of its tests are added too.
+    pub fn render(&self, snapshot: Option<&Snapshot>) -> String {
+        let endpoints = self.endpoints.lock().unwrap();
+        let mut out = String::new();
+
+        out.push_str("# HELP ballast_requests_total Requests sent, by response status.\n");
+        out.push_str("# TYPE ballast_requests_total counter\n");
+        for e in endpoints.iter() {
+            for (status, count) in &e.requests {
+                let status = status.to_string();
+                let labels = self.labels(&e.name, &e.method, &[("status", &status)]);
+                writeln!(out, "ballast_requests_total{} {}", labels, count).ok();
+            }
+        }
+
+        out.push_str("# HELP ballast_request_errors_total Failed requests, by kind of error.\n");
+        out.push_str("# TYPE ballast_request_errors_total counter\n");
+        for e in endpoints.iter() {
+            for (kind, count) in &e.errors {
+                let labels = self.labels(&e.name, &e.method, &[("kind", kind)]);
+                writeln!(out, "ballast_request_errors_total{} {}", labels, count).ok();
+            }
+        }
+
+        out.push_str("# HELP ballast_requests_in_flight Requests waiting for a response.\n");
+        out.push_str("# TYPE ballast_requests_in_flight gauge\n");
+        for e in endpoints.iter() {
+            let labels = self.labels(&e.name, &e.method, &[]);
+            writeln!(out, "ballast_requests_in_flight{} {}", labels, e.in_flight).ok();
+        }
+
+        out.push_str("# HELP ballast_response_bytes_total Size of the response bodies.\n");
+        out.push_str("# TYPE ballast_response_bytes_total counter\n");
+        for e in endpoints.iter() {
+            let labels = self.labels(&e.name, &e.method, &[]);
+            writeln!(out, "ballast_response_bytes_total{} {}", labels, e.bytes).ok();
+        }
+
+        out.push_str("# HELP ballast_request_duration_seconds Response times of the requests.\n");
+        out.push_str("# TYPE ballast_request_duration_seconds histogram\n");
+        for e in endpoints.iter() {
+            let mut cumulative = 0;
+            for (i, count) in e.buckets.iter().enumerate() {
+                cumulative += count;
+                let bound = match BUCKETS.get(i) {
+                    Some(bound) => bound.to_string(),
+                    None => "+Inf".to_string(),
+                };
+                let labels = self.labels(&e.name, &e.method, &[("le", &bound)]);
+                writeln!(
+                    out,
+                    "ballast_request_duration_seconds_bucket{} {}",
+                    labels, cumulative
+                )
+                .ok();
+            }
+            let labels = self.labels(&e.name, &e.method, &[]);
+            writeln!(
+                out,
+                "ballast_request_duration_seconds_sum{} {}",
+                labels, e.sum
+            )
+            .ok();
+            writeln!(
+                out,
+                "ballast_request_duration_seconds_count{} {}",
+                labels, e.count
+            )
+            .ok();
+        }
+
+        if let Some(snapshot) = snapshot {
+            let method = |name: &str| {
+                endpoints
+                    .iter()
+                    .find(|e| e.name == name)
+                    .map(|e| e.method.clone())
+                    .unwrap_or_default()
+            };
+
+            out.push_str("# HELP ballast_test_success Whether the test passed.\n");
+            out.push_str("# TYPE ballast_test_success gauge\n");
+            for test in &snapshot.tests {
+                let name = &test.config.endpoint_name;
+                let labels = self.labels(name, &method(name), &[]);
+                writeln!(out, "ballast_test_success{} {}", labels, test.success as u8).ok();
+            }
+
+            out.push_str(
+                "# HELP ballast_test_response_time_seconds Response time stats of the test.\n",
+            );
+            out.push_str("# TYPE ballast_test_response_time_seconds gauge\n");
+            for test in &snapshot.tests {
+                let name = &test.config.endpoint_name;
+                for (stat, value) in [
+                    ("average", test.stats.average_response_time),
+                    ("min", test.stats.min_response_time),
+                    ("max", test.stats.max_response_time),
+                ] {
+                    let labels = self.labels(name, &method(name), &[("stat", stat)]);
+                    writeln!(
+                        out,
+                        "ballast_test_response_time_seconds{} {}",
+                        labels,
+                        value / 1000.0
+                    )
+                    .ok();
+                }
+            }
+
+            out.push_str("# HELP ballast_run_timestamp_seconds When the run finished.\n");
+            out.push_str("# TYPE ballast_run_timestamp_seconds gauge\n");
+            let labels = match &self.desc {
+                Some(desc) => format!("{{desc=\"{}\"}}", escape(desc)),
+                None => String::new(),
+            };
+            writeln!(
+                out,
+                "ballast_run_timestamp_seconds{} {}",
+                labels, snapshot.timestamp
+            )
+            .ok();
+        }
+
+        out
+    }
+
+    /// Writes the metrics of a finished run for the node exporter textfile
+    /// collector. The file is replaced in one go so the collector never reads
+    /// half of it.
+    pub fn write_file(&self, path: &str, snapshot: &Snapshot) -> Result<()> {
+        let tmp_path = format!("{}.tmp", path);
+        std::fs::write(&tmp_path, self.render(Some(snapshot)))
+            .with_context(|| format!("Failed to write {}", tmp_path))?;
+        std::fs::rename(&tmp_path, path).with_context(|| format!("Failed to write {}", path))?;
+        Ok(())
+    }
+
+    /// Serves `/metrics` on `addr` until the process exits.
+    pub async fn serve(self: &Arc<Self>, addr: SocketAddr) -> Result<()> {
+        let listener = TcpListener::bind(addr)
+            .await
+            .with_context(|| format!("Failed to listen on {}", addr))?;
+        let metrics = self.clone();
+
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let metrics = metrics.clone();
+                tokio::spawn(async move {
+                    let mut buf = [0; 1024];
+                    let n = stream.read(&mut buf).await.unwrap_or(0);
+                    let request = String::from_utf8_lossy(&buf[..n]);
+                    let path = request.split_whitespace().nth(1).unwrap_or("");
+
+                    let response = match path {
+                        "/metrics" => {
+                            let body = metrics.render(None);
+                            format!(
+                                "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                                body.len(),
+                                body
+                            )
+                        }
+                        _ => "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                            .to_string(),
+                    };
+                    stream.write_all(response.as_bytes()).await.ok();
+                    stream.shutdown().await.ok();
+                });
+            }
+        });
+
+        Ok(())
+    }
+}
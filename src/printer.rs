@@ -12,10 +12,10 @@ enum TextStyle {
 }
 
 fn get_sign_string(num: f64) -> String {
-    return match num >= 0.0 {
+    match num >= 0.0 {
         true => "+".to_string(),
         false => "".to_string(),
-    };
+    }
 }
 
 fn get_color(color: Color, text_style: Option<TextStyle>) -> Style {
@@ -31,6 +31,7 @@ fn get_color(color: Color, text_style: Option<TextStyle>) -> Style {
     }
 }
 
+#[derive(Clone)]
 pub struct Printer {
     term: Term,
 }
@@ -105,11 +106,31 @@ impl Printer {
         self
     }
 
-    pub fn clear_previous(&self) -> &Self {
-        self.term.clear_last_lines(1).ok();
+    pub fn print_plain(&self, description: &str, indent: i8) -> &Self {
+        let _res = self.term.write_line(&format!(
+            "{}{}",
+            " ".repeat(indent as usize),
+            get_color(Color::White, None).apply_to(description)
+        ));
         self
     }
 
+    pub fn is_term(&self) -> bool {
+        self.term.is_term()
+    }
+
+    // Clearing only makes sense on a terminal, piped output keeps every line.
+    pub fn clear_lines(&self, count: usize) -> &Self {
+        if self.is_term() && count > 0 {
+            self.term.clear_last_lines(count).ok();
+        }
+        self
+    }
+
+    pub fn clear_previous(&self) -> &Self {
+        self.clear_lines(1)
+    }
+
     pub fn blank_line(&self) -> &Self {
         self.term.write_line("").ok();
         self
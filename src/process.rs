@@ -1,5 +1,6 @@
-use crate::config::{Config, EndpointConfig};
-use crate::runner::Loads;
+use crate::config::{Config, EndpointConfig, ThresholdMode};
+use crate::runner::{Loads, SingleCycle};
+use crate::schema;
 use crate::snapshot::Snapshot;
 use serde::{Deserialize, Serialize};
 
@@ -15,16 +16,17 @@ pub struct Expected {
     pub body: Option<bool>,
     pub status_code: Option<bool>,
     pub headers: Option<bool>,
+    pub schema: Option<bool>,
 }
 
 impl Expected {
     pub fn passes(&self) -> bool {
-        let is_truthy = |e: Option<bool>| match e {
-            Some(e) => e,
-            None => true,
-        };
+        let is_truthy = |e: Option<bool>| e.unwrap_or(true);
 
-        is_truthy(self.body) && is_truthy(self.status_code) && is_truthy(self.headers)
+        is_truthy(self.body)
+            && is_truthy(self.status_code)
+            && is_truthy(self.headers)
+            && is_truthy(self.schema)
     }
 }
 
@@ -43,6 +45,61 @@ pub struct Test {
     pub expected: Expected,
     pub stats: LoadStats,
     pub config: SimpleConfig,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub samples: Option<Vec<Sample>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub noise: Option<Noise>,
+}
+
+/// How much the average response time varied between the runs of a test run
+/// with `--repeat`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Noise {
+    pub averages: Vec<f64>,
+    pub std_dev: f64,
+}
+
+impl Noise {
+    fn new(averages: Vec<f64>) -> Self {
+        let mean = averages.iter().sum::<f64>() / averages.len() as f64;
+        let variance =
+            averages.iter().map(|a| (a - mean).powi(2)).sum::<f64>() / (averages.len() - 1) as f64;
+        Self {
+            averages,
+            std_dev: variance.sqrt(),
+        }
+    }
+}
+
+/// How far in ms the average response time may be above the baseline's. With
+/// the noise mode it's a multiple of how much the baseline varied between its
+/// runs, falling back to the fixed threshold when it wasn't repeated.
+pub fn allowed_deviation(endpoint: &EndpointConfig, baseline: &Test) -> f64 {
+    match (endpoint.threshold_mode, &baseline.noise) {
+        (Some(ThresholdMode::Noise), Some(noise)) => {
+            (endpoint.noise_factor.unwrap_or(3.0) * noise.std_dev).max(1.0)
+        }
+        _ => endpoint.threshold.unwrap_or(250) as f64,
+    }
+}
+
+/// A single request of a test, `offset` is the time in ms from the start of the
+/// load to the start of the request.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Sample {
+    pub offset: u64,
+    pub duration: u64,
+    pub status: u16,
+}
+
+/// The value at percentile `p` (0-100) of already sorted values, using the
+/// nearest rank.
+pub fn percentile(sorted: &[f64], p: f64) -> f64 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+    let rank = (p / 100.0 * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
 }
 type Expectations = Vec<Expected>;
 
@@ -53,18 +110,22 @@ trait ReduceExpectations {
 impl ReduceExpectations for Expectations {
     fn reduce_expectations(&self, endpoint: &EndpointConfig) -> Expected {
         Expected {
-            body: match endpoint.expected_body {
-                Some(_) => Some(self.iter().all(|e| e.body == Some(true))),
-                None => None,
-            },
-            status_code: match endpoint.expected_status {
-                Some(_) => Some(self.iter().all(|e| e.status_code == Some(true))),
-                None => None,
-            },
-            headers: match endpoint.expected_headers {
-                Some(_) => Some(self.iter().all(|e| e.headers == Some(true))),
-                None => None,
-            },
+            body: endpoint
+                .expected_body
+                .as_ref()
+                .map(|_| self.iter().all(|e| e.body == Some(true))),
+            status_code: endpoint
+                .expected_status
+                .as_ref()
+                .map(|_| self.iter().all(|e| e.status_code == Some(true))),
+            headers: endpoint
+                .expected_headers
+                .as_ref()
+                .map(|_| self.iter().all(|e| e.headers == Some(true))),
+            schema: endpoint
+                .response_schema
+                .as_ref()
+                .map(|_| self.iter().all(|e| e.schema == Some(true))),
         }
     }
 }
@@ -83,10 +144,9 @@ pub fn process(loads: &Loads, config: &Config, snapshot: Option<&Snapshot>) -> V
         for cycle in load.cycles.iter() {
             let mut response_expected: Vec<Expected> = vec![];
             for request in cycle.iter() {
-                let does_status_match = match endpoint.expected_status {
-                    Some(status) => Some(status == request.status),
-                    None => None,
-                };
+                let does_status_match = endpoint
+                    .expected_status
+                    .map(|status| status == request.status);
 
                 let does_body_match = match [
                     endpoint.expected_body.clone(),
@@ -108,11 +168,21 @@ pub fn process(loads: &Loads, config: &Config, snapshot: Option<&Snapshot>) -> V
                     [Some(expected), Some(actual)] => Some(expected == actual),
                 };
 
+                let does_schema_match = match [
+                    endpoint.response_schema.as_ref(),
+                    request.response_body.as_ref(),
+                ] {
+                    [None, _] => None,
+                    [Some(_), None] => Some(false),
+                    [Some(schema), Some(actual)] => Some(schema::validate(schema, actual)),
+                };
+
                 // Does this request match the expected tests?
                 let expected = Expected {
                     body: does_body_match,
                     status_code: does_status_match,
                     headers: does_headers_match,
+                    schema: does_schema_match,
                 };
 
                 response_expected.push(expected);
@@ -124,12 +194,30 @@ pub fn process(loads: &Loads, config: &Config, snapshot: Option<&Snapshot>) -> V
 
         let expected = cycles_expected.reduce_expectations(endpoint);
 
-        let average_response_time = load
-            .cycles
-            .iter()
-            .map(|c| c.iter().map(|r| r.duration).sum::<u128>() / c.len() as u128)
-            .sum::<u128>()
-            / load.cycles.len() as u128;
+        let average = |cycles: &[SingleCycle]| {
+            cycles
+                .iter()
+                .map(|c| c.iter().map(|r| r.duration).sum::<u128>() / c.len() as u128)
+                .sum::<u128>()
+                / cycles.len() as u128
+        };
+        let average_response_time = average(&load.cycles);
+        let noise = match load.runs.len() > 1 {
+            true => {
+                let mut start = 0;
+                let averages = load
+                    .runs
+                    .iter()
+                    .map(|&run| {
+                        let run_average = average(&load.cycles[start..start + run]);
+                        start += run;
+                        run_average as f64
+                    })
+                    .collect();
+                Some(Noise::new(averages))
+            }
+            false => None,
+        };
         let max_response_time = load
             .cycles
             .iter()
@@ -149,42 +237,46 @@ pub fn process(loads: &Loads, config: &Config, snapshot: Option<&Snapshot>) -> V
             min_response_time: min_response_time as f64,
         };
 
-        let test = match snapshot {
-            Some(snapshot) => {
-                let latest_test = snapshot
-                    .tests
-                    .iter()
-                    .find(|t| t.config.endpoint_name == load.endpoint_name)
-                    .unwrap();
-                let within_threshold = stats.average_response_time
+        let load_start = load.cycles.iter().flatten().map(|r| r.start).min().unwrap();
+        let samples = load
+            .cycles
+            .iter()
+            .flatten()
+            .map(|r| Sample {
+                offset: r.start.duration_since(load_start).as_millis() as u64,
+                duration: r.duration as u64,
+                status: r.status,
+            })
+            .collect::<Vec<_>>();
+
+        let latest_test = snapshot.and_then(|snapshot| {
+            snapshot
+                .tests
+                .iter()
+                .find(|t| t.config.endpoint_name == load.endpoint_name)
+        });
+        let within_threshold = match latest_test {
+            Some(latest_test) => {
+                stats.average_response_time
                     < latest_test.stats.average_response_time
-                        + endpoint.threshold.unwrap_or(250) as f64;
-
-                Test {
-                    success: expected.passes() && within_threshold,
-                    within_threshold,
-                    expected,
-                    stats,
-                    config: SimpleConfig {
-                        num_cycles: load.cycles.len() as u64,
-                        num_concurrent_requests: load.cycles[0].len() as u64,
-                        endpoint_name: load.endpoint_name.clone(),
-                        endpoint_url: endpoint.url.clone(),
-                    },
-                }
+                        + allowed_deviation(endpoint, latest_test)
             }
-            None => Test {
-                success: expected.passes(),
-                within_threshold: true,
-                expected,
-                stats,
-                config: SimpleConfig {
-                    num_cycles: load.cycles.len() as u64,
-                    num_concurrent_requests: load.cycles[0].len() as u64,
-                    endpoint_name: load.endpoint_name.clone(),
-                    endpoint_url: endpoint.url.clone(),
-                },
+            None => true,
+        };
+
+        let test = Test {
+            success: expected.passes() && within_threshold,
+            within_threshold,
+            expected,
+            stats,
+            config: SimpleConfig {
+                num_cycles: load.num_cycles,
+                num_concurrent_requests: load.num_concurrent_requests,
+                endpoint_name: load.endpoint_name.clone(),
+                endpoint_url: load.endpoint_url.clone(),
             },
+            samples: Some(samples),
+            noise,
         };
 
         tests.push(test);
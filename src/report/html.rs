@@ -0,0 +1,427 @@
+use super::format_timestamp;
+use crate::process::{percentile, Test};
+use crate::snapshot::Snapshot;
+
+const WIDTH: f64 = 640.0;
+const HEIGHT: f64 = 260.0;
+const PADDING: f64 = 48.0;
+
+const CURRENT_COLOR: &str = "#2563eb";
+const BASELINE_COLOR: &str = "#9ca3af";
+const HIGHLIGHT_COLOR: &str = "#dc2626";
+
+enum SeriesKind {
+    Line,
+    Points,
+    Bars,
+}
+
+struct Series {
+    name: String,
+    color: &'static str,
+    kind: SeriesKind,
+    points: Vec<(f64, f64)>,
+}
+
+struct Chart {
+    title: String,
+    x_label: &'static str,
+    y_label: &'static str,
+    series: Vec<Series>,
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn format_tick(value: f64) -> String {
+    match value.fract() == 0.0 || value.abs() >= 100.0 {
+        true => format!("{:.0}", value),
+        false => format!("{:.1}", value),
+    }
+}
+
+/// Draws a chart as an inline svg, so the report works without any scripts or
+/// network access.
+fn render_chart(chart: &Chart) -> String {
+    let points = chart.series.iter().flat_map(|s| s.points.iter());
+    let (mut x_min, mut x_max, mut y_max) = (f64::MAX, f64::MIN, 0.0_f64);
+    for (x, y) in points {
+        x_min = x_min.min(*x);
+        x_max = x_max.max(*x);
+        y_max = y_max.max(*y);
+    }
+    if x_min > x_max {
+        (x_min, x_max) = (0.0, 1.0);
+    }
+    if x_min == x_max {
+        x_max = x_min + 1.0;
+    }
+    if y_max == 0.0 {
+        y_max = 1.0;
+    }
+    y_max *= 1.1;
+
+    let plot_width = WIDTH - PADDING * 2.0;
+    let plot_height = HEIGHT - PADDING * 2.0;
+    let scale_x = |x: f64| PADDING + (x - x_min) / (x_max - x_min) * plot_width;
+    let scale_y = |y: f64| HEIGHT - PADDING - y / y_max * plot_height;
+
+    let mut svg = format!(
+        "<svg viewBox=\"0 0 {} {}\" xmlns=\"http://www.w3.org/2000/svg\" role=\"img\">\n",
+        WIDTH, HEIGHT
+    );
+    svg.push_str(&format!(
+        "<text x=\"{}\" y=\"20\" class=\"title\">{}</text>\n",
+        PADDING,
+        escape(&chart.title)
+    ));
+
+    for i in 0..=4 {
+        let y = y_max / 4.0 * i as f64;
+        svg.push_str(&format!(
+            "<line x1=\"{x1}\" x2=\"{x2}\" y1=\"{y}\" y2=\"{y}\" class=\"grid\"/>\
+             <text x=\"{tx}\" y=\"{ty}\" class=\"tick\" text-anchor=\"end\">{label}</text>\n",
+            x1 = PADDING,
+            x2 = WIDTH - PADDING,
+            y = scale_y(y),
+            tx = PADDING - 6.0,
+            ty = scale_y(y) + 4.0,
+            label = format_tick(y),
+        ));
+        let x = x_min + (x_max - x_min) / 4.0 * i as f64;
+        svg.push_str(&format!(
+            "<text x=\"{}\" y=\"{}\" class=\"tick\" text-anchor=\"middle\">{}</text>\n",
+            scale_x(x),
+            HEIGHT - PADDING + 16.0,
+            format_tick(x),
+        ));
+    }
+    svg.push_str(&format!(
+        "<text x=\"{}\" y=\"{}\" class=\"label\" text-anchor=\"middle\">{}</text>\n",
+        WIDTH / 2.0,
+        HEIGHT - 8.0,
+        chart.x_label
+    ));
+    svg.push_str(&format!(
+        "<text x=\"12\" y=\"{}\" class=\"label\" text-anchor=\"middle\" transform=\"rotate(-90 12 {})\">{}</text>\n",
+        HEIGHT / 2.0,
+        HEIGHT / 2.0,
+        chart.y_label
+    ));
+
+    for series in &chart.series {
+        match series.kind {
+            SeriesKind::Line => {
+                let path = series
+                    .points
+                    .iter()
+                    .map(|(x, y)| format!("{:.1},{:.1}", scale_x(*x), scale_y(*y)))
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                svg.push_str(&format!(
+                    "<polyline points=\"{}\" fill=\"none\" stroke=\"{}\" stroke-width=\"2\"/>\n",
+                    path, series.color
+                ));
+            }
+            SeriesKind::Points => {
+                for (x, y) in &series.points {
+                    svg.push_str(&format!(
+                        "<circle cx=\"{:.1}\" cy=\"{:.1}\" r=\"3\" fill=\"{}\" fill-opacity=\"0.6\"/>\n",
+                        scale_x(*x),
+                        scale_y(*y),
+                        series.color
+                    ));
+                }
+            }
+            SeriesKind::Bars => {
+                let step = match series.points.len() {
+                    0 | 1 => plot_width,
+                    n => {
+                        (scale_x(series.points[n - 1].0) - scale_x(series.points[0].0))
+                            / (n - 1) as f64
+                    }
+                };
+                for (x, y) in &series.points {
+                    svg.push_str(&format!(
+                        "<rect x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{:.1}\" fill=\"{}\" fill-opacity=\"0.5\" stroke=\"{}\"/>\n",
+                        scale_x(*x),
+                        scale_y(*y),
+                        step.max(1.0),
+                        HEIGHT - PADDING - scale_y(*y),
+                        series.color,
+                        series.color
+                    ));
+                }
+            }
+        }
+    }
+
+    for (i, series) in chart.series.iter().enumerate() {
+        let x = WIDTH - PADDING - 140.0;
+        let y = 16.0 + i as f64 * 14.0;
+        svg.push_str(&format!(
+            "<rect x=\"{}\" y=\"{}\" width=\"10\" height=\"10\" fill=\"{}\"/>\
+             <text x=\"{}\" y=\"{}\" class=\"tick\">{}</text>\n",
+            x,
+            y - 9.0,
+            series.color,
+            x + 14.0,
+            y,
+            escape(&series.name)
+        ));
+    }
+
+    svg.push_str("</svg>\n");
+    svg
+}
+
+fn sorted_durations(test: &Test) -> Vec<f64> {
+    let mut durations = test
+        .samples
+        .iter()
+        .flatten()
+        .map(|s| s.duration as f64)
+        .collect::<Vec<_>>();
+    durations.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    durations
+}
+
+fn histogram(current: &[f64], baseline: &[f64]) -> Vec<Series> {
+    let max = current.iter().chain(baseline).cloned().fold(0.0, f64::max);
+    let bins = 20;
+    let width = (max / bins as f64).max(1.0);
+    let count = |durations: &[f64]| {
+        let mut counts = vec![0.0; bins + 1];
+        for duration in durations {
+            counts[((duration / width) as usize).min(bins)] += 1.0;
+        }
+        counts
+            .into_iter()
+            .enumerate()
+            .map(|(i, c)| (i as f64 * width, c))
+            .collect::<Vec<_>>()
+    };
+
+    let mut series = vec![];
+    if !baseline.is_empty() {
+        series.push(Series {
+            name: "baseline".to_string(),
+            color: BASELINE_COLOR,
+            kind: SeriesKind::Bars,
+            points: count(baseline),
+        });
+    }
+    series.push(Series {
+        name: "current".to_string(),
+        color: CURRENT_COLOR,
+        kind: SeriesKind::Bars,
+        points: count(current),
+    });
+    series
+}
+
+fn percentile_curve(name: &str, color: &'static str, durations: &[f64]) -> Series {
+    Series {
+        name: name.to_string(),
+        color,
+        kind: SeriesKind::Line,
+        points: (0..=100)
+            .map(|p| (p as f64, percentile(durations, p as f64)))
+            .collect(),
+    }
+}
+
+/// Renders a single offline html page with charts of every test of the
+/// `current` snapshot, compared against its `baseline` and the whole history.
+pub fn render(current: &Snapshot, baseline: Option<&Snapshot>, history: &[Snapshot]) -> String {
+    let mut body = String::new();
+
+    for test in &current.tests {
+        let name = &test.config.endpoint_name;
+        let baseline_test =
+            baseline.and_then(|b| b.tests.iter().find(|t| &t.config.endpoint_name == name));
+        let durations = sorted_durations(test);
+        let baseline_durations = baseline_test.map(sorted_durations).unwrap_or_default();
+
+        body.push_str(&format!(
+            "<section>\n<h2>{} <span class=\"{}\">{}</span></h2>\n<p class=\"url\">{}</p>\n",
+            escape(name),
+            match test.success {
+                true => "pass",
+                false => "fail",
+            },
+            match test.success {
+                true => "PASS",
+                false => "FAIL",
+            },
+            escape(&test.config.endpoint_url)
+        ));
+
+        body.push_str("<table>\n<tr><th></th><th>avg</th><th>min</th><th>max</th><th>p50</th><th>p90</th><th>p99</th></tr>\n");
+        for (label, stats_test, stats_durations) in [
+            ("current", Some(test), &durations),
+            ("baseline", baseline_test, &baseline_durations),
+        ] {
+            if let Some(stats_test) = stats_test {
+                let p = |p: f64| match stats_durations.is_empty() {
+                    true => "-".to_string(),
+                    false => format!("{}ms", percentile(stats_durations, p)),
+                };
+                body.push_str(&format!(
+                    "<tr><td>{}</td><td>{}ms</td><td>{}ms</td><td>{}ms</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+                    label,
+                    stats_test.stats.average_response_time,
+                    stats_test.stats.min_response_time,
+                    stats_test.stats.max_response_time,
+                    p(50.0),
+                    p(90.0),
+                    p(99.0),
+                ));
+            }
+        }
+        body.push_str("</table>\n<div class=\"charts\">\n");
+
+        if durations.is_empty() {
+            body.push_str(
+                "<p>This snapshot has no per request samples, only the trend can be shown.</p>\n",
+            );
+        } else {
+            body.push_str(&render_chart(&Chart {
+                title: "Latency histogram".to_string(),
+                x_label: "response time (ms)",
+                y_label: "requests",
+                series: histogram(&durations, &baseline_durations),
+            }));
+
+            let mut curves = vec![];
+            if !baseline_durations.is_empty() {
+                curves.push(percentile_curve(
+                    "baseline",
+                    BASELINE_COLOR,
+                    &baseline_durations,
+                ));
+            }
+            curves.push(percentile_curve("current", CURRENT_COLOR, &durations));
+            body.push_str(&render_chart(&Chart {
+                title: "Percentiles".to_string(),
+                x_label: "percentile",
+                y_label: "response time (ms)",
+                series: curves,
+            }));
+
+            body.push_str(&render_chart(&Chart {
+                title: "Latency during the run".to_string(),
+                x_label: "time since start (ms)",
+                y_label: "response time (ms)",
+                series: vec![Series {
+                    name: "current".to_string(),
+                    color: CURRENT_COLOR,
+                    kind: SeriesKind::Points,
+                    points: test
+                        .samples
+                        .iter()
+                        .flatten()
+                        .map(|s| (s.offset as f64, s.duration as f64))
+                        .collect(),
+                }],
+            }));
+        }
+
+        // Snapshots are numbered in the order they were taken.
+        let trend = history
+            .iter()
+            .enumerate()
+            .filter_map(|(i, snapshot)| {
+                let t = snapshot
+                    .tests
+                    .iter()
+                    .find(|t| &t.config.endpoint_name == name)?;
+                Some((
+                    i as f64 + 1.0,
+                    t.stats.average_response_time,
+                    snapshot.timestamp,
+                ))
+            })
+            .collect::<Vec<_>>();
+        let highlight = |timestamp: u64| {
+            trend
+                .iter()
+                .filter(|(_, _, t)| *t == timestamp)
+                .map(|(x, y, _)| (*x, *y))
+                .collect::<Vec<_>>()
+        };
+        let mut trend_series = vec![Series {
+            name: "avg response time".to_string(),
+            color: CURRENT_COLOR,
+            kind: SeriesKind::Line,
+            points: trend.iter().map(|(x, y, _)| (*x, *y)).collect(),
+        }];
+        if let Some(baseline) = baseline {
+            trend_series.push(Series {
+                name: "baseline".to_string(),
+                color: BASELINE_COLOR,
+                kind: SeriesKind::Points,
+                points: highlight(baseline.timestamp),
+            });
+        }
+        trend_series.push(Series {
+            name: "current".to_string(),
+            color: HIGHLIGHT_COLOR,
+            kind: SeriesKind::Points,
+            points: highlight(current.timestamp),
+        });
+        body.push_str(&render_chart(&Chart {
+            title: "Trend across snapshots".to_string(),
+            x_label: "snapshot",
+            y_label: "avg response time (ms)",
+            series: trend_series,
+        }));
+
+        body.push_str("</div>\n</section>\n");
+    }
+
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<title>Ballast report</title>
+<style>
+body {{ font-family: -apple-system, BlinkMacSystemFont, "Segoe UI", sans-serif; margin: 2rem auto; max-width: 1360px; color: #111827; }}
+section {{ border-top: 1px solid #e5e7eb; padding: 1rem 0; }}
+h2 span {{ font-size: 0.8rem; padding: 0.1rem 0.4rem; border-radius: 4px; color: white; vertical-align: middle; }}
+.pass {{ background: #16a34a; }}
+.fail {{ background: #dc2626; }}
+.url {{ color: #6b7280; font-family: monospace; }}
+table {{ border-collapse: collapse; margin-bottom: 1rem; }}
+td, th {{ padding: 0.2rem 0.8rem; text-align: right; border-bottom: 1px solid #e5e7eb; }}
+.charts {{ display: grid; grid-template-columns: repeat(auto-fill, minmax(560px, 1fr)); gap: 1rem; }}
+.title {{ font-size: 14px; font-weight: 600; }}
+.tick {{ font-size: 10px; fill: #6b7280; }}
+.label {{ font-size: 11px; fill: #374151; }}
+.grid {{ stroke: #e5e7eb; }}
+</style>
+</head>
+<body>
+<h1>⚓ Ballast report</h1>
+<p>Snapshot taken at {current}{baseline}, {count} snapshots in history.</p>
+{body}
+</body>
+</html>
+"#,
+        current = format_timestamp(current.timestamp),
+        baseline = match baseline {
+            Some(b) => format!(
+                ", compared against the snapshot taken at {}",
+                format_timestamp(b.timestamp)
+            ),
+            None => String::new(),
+        },
+        count = history.len(),
+        body = body,
+    )
+}
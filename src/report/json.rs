@@ -0,0 +1,126 @@
+use crate::compare::{failure_reasons, find_baseline, find_endpoint, metrics};
+use crate::config::Config;
+use crate::process::{Expected, LoadStats, Test};
+use crate::snapshot::Snapshot;
+use anyhow::Result;
+use serde::Serialize;
+
+/// Version of the json report layout, bumped whenever a field is removed or
+/// changes meaning. Adding fields doesn't change the version.
+pub const SCHEMA_VERSION: u32 = 1;
+
+#[derive(Serialize)]
+pub struct JsonReport<'a> {
+    pub schema_version: u32,
+    pub ballast_version: &'static str,
+    pub timestamp: u64,
+    pub success: bool,
+    pub baseline: Option<JsonBaseline>,
+    pub tests: Vec<JsonTest<'a>>,
+}
+
+#[derive(Serialize)]
+pub struct JsonBaseline {
+    pub timestamp: u64,
+}
+
+#[derive(Serialize)]
+pub struct JsonTest<'a> {
+    pub name: &'a str,
+    pub url: &'a str,
+    pub method: String,
+    pub success: bool,
+    pub within_threshold: bool,
+    pub threshold: u128,
+    pub cycles: u64,
+    pub concurrent_requests: u64,
+    pub expected: &'a Expected,
+    pub stats: &'a LoadStats,
+    pub baseline_stats: Option<&'a LoadStats>,
+    pub metrics: Vec<JsonMetric>,
+    pub failures: Vec<JsonFailure>,
+}
+
+#[derive(Serialize)]
+pub struct JsonMetric {
+    pub name: &'static str,
+    pub unit: &'static str,
+    pub current: f64,
+    pub baseline: Option<f64>,
+    pub delta: Option<f64>,
+    pub percent_change: Option<f64>,
+}
+
+#[derive(Serialize)]
+pub struct JsonFailure {
+    pub kind: String,
+    pub message: String,
+}
+
+fn build<'a>(
+    tests: &'a [Test],
+    config: &'a Config,
+    latest: Option<&'a Snapshot>,
+    timestamp: u64,
+) -> JsonReport<'a> {
+    let tests = tests
+        .iter()
+        .map(|test| {
+            let endpoint = find_endpoint(test, config);
+            let baseline = find_baseline(test, latest);
+            JsonTest {
+                name: &test.config.endpoint_name,
+                url: &test.config.endpoint_url,
+                method: endpoint.method.to_string(),
+                success: test.success,
+                within_threshold: test.within_threshold,
+                threshold: endpoint.threshold.unwrap_or(250),
+                cycles: test.config.num_cycles,
+                concurrent_requests: test.config.num_concurrent_requests,
+                expected: &test.expected,
+                stats: &test.stats,
+                baseline_stats: baseline.map(|b| &b.stats),
+                metrics: metrics(test, baseline)
+                    .into_iter()
+                    .map(|m| JsonMetric {
+                        name: m.name,
+                        unit: m.unit,
+                        current: m.current,
+                        baseline: m.baseline,
+                        delta: m.delta(),
+                        percent_change: m.percent_change(),
+                    })
+                    .collect(),
+                failures: failure_reasons(test, endpoint, baseline)
+                    .into_iter()
+                    .map(|r| JsonFailure {
+                        kind: r.kind.to_string(),
+                        message: r.message,
+                    })
+                    .collect(),
+            }
+        })
+        .collect::<Vec<_>>();
+
+    JsonReport {
+        schema_version: SCHEMA_VERSION,
+        ballast_version: env!("CARGO_PKG_VERSION"),
+        timestamp,
+        success: tests.iter().all(|t| t.success),
+        baseline: latest.map(|s| JsonBaseline {
+            timestamp: s.timestamp,
+        }),
+        tests,
+    }
+}
+
+pub fn render(
+    tests: &[Test],
+    config: &Config,
+    latest: Option<&Snapshot>,
+    timestamp: u64,
+) -> Result<String> {
+    Ok(serde_json::to_string_pretty(&build(
+        tests, config, latest, timestamp,
+    ))?)
+}
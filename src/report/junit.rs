@@ -0,0 +1,95 @@
+use crate::compare::{failure_reasons, find_baseline, find_endpoint, metrics};
+use crate::config::Config;
+use crate::process::Test;
+use crate::snapshot::Snapshot;
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+fn property(name: &str, value: impl std::fmt::Display) -> String {
+    format!(
+        "        <property name=\"{}\" value=\"{}\"/>\n",
+        escape(name),
+        escape(&value.to_string())
+    )
+}
+
+/// Renders every test as a JUnit testcase, with the failure reasons of failing
+/// tests and the load stats (and their change against the baseline) as
+/// properties.
+pub fn render(tests: &[Test], config: &Config, latest: Option<&Snapshot>) -> String {
+    let failures = tests.iter().filter(|t| !t.success).count();
+    let mut cases = String::new();
+
+    for test in tests {
+        let endpoint = find_endpoint(test, config);
+        let baseline = find_baseline(test, latest);
+
+        cases.push_str(&format!(
+            "    <testcase name=\"{}\" classname=\"ballast.{}\">\n",
+            escape(&test.config.endpoint_name),
+            endpoint.method
+        ));
+
+        cases.push_str("      <properties>\n");
+        cases.push_str(&property("url", &test.config.endpoint_url));
+        cases.push_str(&property("cycles", test.config.num_cycles));
+        cases.push_str(&property(
+            "concurrent_requests",
+            test.config.num_concurrent_requests,
+        ));
+        for metric in metrics(test, baseline) {
+            cases.push_str(&property(metric.name, metric.current));
+            if let (Some(baseline), Some(delta)) = (metric.baseline, metric.delta()) {
+                cases.push_str(&property(&format!("baseline_{}", metric.name), baseline));
+                cases.push_str(&property(&format!("delta_{}", metric.name), delta));
+            }
+        }
+        cases.push_str("      </properties>\n");
+
+        if !test.success {
+            let reasons = failure_reasons(test, endpoint, baseline);
+            let message = match reasons.first() {
+                Some(reason) => reason.message.clone(),
+                None => "test failed".to_string(),
+            };
+            let kind = match reasons.first() {
+                Some(reason) => reason.kind.to_string(),
+                None => "failure".to_string(),
+            };
+            let details = reasons
+                .iter()
+                .map(|r| format!("{}: {}", r.kind.title(), r.message))
+                .collect::<Vec<_>>()
+                .join("\n");
+            cases.push_str(&format!(
+                "      <failure message=\"{}\" type=\"{}\">{}</failure>\n",
+                escape(&message),
+                kind,
+                escape(&details)
+            ));
+        }
+
+        cases.push_str("    </testcase>\n");
+    }
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str(&format!(
+        "<testsuites name=\"ballast\" tests=\"{}\" failures=\"{}\">\n",
+        tests.len(),
+        failures
+    ));
+    xml.push_str(&format!(
+        "  <testsuite name=\"ballast\" tests=\"{}\" failures=\"{}\" errors=\"0\" skipped=\"0\">\n",
+        tests.len(),
+        failures
+    ));
+    xml.push_str(&cases);
+    xml.push_str("  </testsuite>\n</testsuites>\n");
+    xml
+}
@@ -0,0 +1,124 @@
+use super::format_timestamp;
+use crate::compare::{failure_reasons, find_baseline, find_endpoint, metrics, Metric};
+use crate::config::Config;
+use crate::process::Test;
+use crate::snapshot::Snapshot;
+
+// Every metric is a response time, so an increase is a regression.
+fn indicator(metric: &Metric) -> &'static str {
+    match metric.delta() {
+        Some(delta) if delta > 0.0 => "🔺",
+        Some(delta) if delta < 0.0 => "🔻",
+        Some(_) => "➖",
+        None => "",
+    }
+}
+
+fn signed(value: f64) -> String {
+    match value >= 0.0 {
+        true => format!("+{}", value),
+        false => value.to_string(),
+    }
+}
+
+fn escape(s: &str) -> String {
+    s.replace('|', "\\|")
+}
+
+/// Renders a summary for pull request comments: a table of the stats of every
+/// test against the baseline and the failure reasons, with the test config in a
+/// collapsed section.
+pub fn render(tests: &[Test], config: &Config, latest: Option<&Snapshot>) -> String {
+    let failures = tests.iter().filter(|t| !t.success).count();
+    let mut md = String::from("## ⚓ Ballast results\n\n");
+
+    md.push_str(&match failures {
+        0 => format!("✅ All {} tests passed", tests.len()),
+        _ => format!("❌ {} of {} tests failed", failures, tests.len()),
+    });
+    md.push_str(&match latest {
+        Some(snapshot) => format!(
+            ", compared against the snapshot from {}.\n\n",
+            format_timestamp(snapshot.timestamp)
+        ),
+        None => ", no previous snapshot to compare against.\n\n".to_string(),
+    });
+
+    for test in tests {
+        let endpoint = find_endpoint(test, config);
+        let baseline = find_baseline(test, latest);
+
+        md.push_str(&format!(
+            "### {} {}\n\n`{} {}`\n\n",
+            match test.success {
+                true => "✅",
+                false => "❌",
+            },
+            test.config.endpoint_name,
+            endpoint.method,
+            test.config.endpoint_url
+        ));
+
+        md.push_str("| Metric | Current | Baseline | Change | % |\n");
+        md.push_str("|:--|--:|--:|--:|--:|\n");
+        for metric in metrics(test, baseline) {
+            md.push_str(&format!(
+                "| {} | {}{} | {} | {} | {} |\n",
+                metric.label,
+                metric.current,
+                metric.unit,
+                metric
+                    .baseline
+                    .map(|b| format!("{}{}", b, metric.unit))
+                    .unwrap_or_else(|| "-".to_string()),
+                metric
+                    .delta()
+                    .map(|d| format!("{} {}{}", indicator(&metric), signed(d), metric.unit))
+                    .unwrap_or_else(|| "-".to_string()),
+                metric
+                    .percent_change()
+                    .map(|p| format!("{}%", signed((p * 10.0).round() / 10.0)))
+                    .unwrap_or_else(|| "-".to_string()),
+            ));
+        }
+        md.push('\n');
+
+        let reasons = failure_reasons(test, endpoint, baseline);
+        if !reasons.is_empty() {
+            md.push_str("**Failures**\n\n");
+            for reason in reasons {
+                md.push_str(&format!(
+                    "- **{}**: {}\n",
+                    reason.kind.title(),
+                    reason.message
+                ));
+            }
+            md.push('\n');
+        }
+    }
+
+    md.push_str("<details>\n<summary>Config</summary>\n\n");
+    md.push_str(
+        "| Test | Method | Url | Cycles | Concurrent requests | Threshold | Expected status |\n",
+    );
+    md.push_str("|:--|:--|:--|--:|--:|--:|--:|\n");
+    for test in tests {
+        let endpoint = find_endpoint(test, config);
+        md.push_str(&format!(
+            "| {} | {} | `{}` | {} | {} | {}ms | {} |\n",
+            escape(&test.config.endpoint_name),
+            endpoint.method,
+            escape(&test.config.endpoint_url),
+            test.config.num_cycles,
+            test.config.num_concurrent_requests,
+            endpoint.threshold.unwrap_or(250),
+            endpoint
+                .expected_status
+                .map(|s| s.to_string())
+                .unwrap_or_else(|| "-".to_string()),
+        ));
+    }
+    md.push_str("\n</details>\n");
+
+    md
+}
@@ -0,0 +1,87 @@
+pub mod html;
+pub mod json;
+pub mod junit;
+pub mod markdown;
+
+use crate::config::Config;
+use crate::snapshot::Snapshot;
+use anyhow::{anyhow, Context, Result};
+use std::str::FromStr;
+
+#[derive(Debug, Clone)]
+pub enum ReportFormat {
+    Junit,
+    Json,
+    Markdown,
+}
+
+/// A report to write at the end of a run, given on the command line as
+/// `<format>=<path>`.
+#[derive(Debug, Clone)]
+pub struct ReportTarget {
+    pub format: ReportFormat,
+    pub path: String,
+}
+
+impl FromStr for ReportTarget {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (format, path) = s
+            .split_once('=')
+            .ok_or_else(|| anyhow!("expected <format>=<path>, e.g. junit=report.xml"))?;
+        let format = match format {
+            "junit" => ReportFormat::Junit,
+            "json" => ReportFormat::Json,
+            "markdown" | "md" => ReportFormat::Markdown,
+            other => return Err(anyhow!("unknown report format {}", other)),
+        };
+        if path.is_empty() {
+            return Err(anyhow!("missing path for {} report", format));
+        }
+
+        Ok(Self {
+            format,
+            path: path.to_string(),
+        })
+    }
+}
+
+impl std::fmt::Display for ReportFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let format = match self {
+            ReportFormat::Junit => "junit",
+            ReportFormat::Json => "json",
+            ReportFormat::Markdown => "markdown",
+        };
+        write!(f, "{}", format)
+    }
+}
+
+/// Writes a report comparing the `current` run against the `latest` snapshot.
+pub fn write_report(
+    target: &ReportTarget,
+    current: &Snapshot,
+    config: &Config,
+    latest: Option<&Snapshot>,
+) -> Result<()> {
+    let contents = match target.format {
+        ReportFormat::Junit => junit::render(&current.tests, config, latest),
+        ReportFormat::Json => json::render(&current.tests, config, latest, current.timestamp)?,
+        ReportFormat::Markdown => markdown::render(&current.tests, config, latest),
+    };
+
+    std::fs::write(&target.path, contents).with_context(|| {
+        format!(
+            "Failed to write {} report to {}",
+            target.format, target.path
+        )
+    })
+}
+
+pub fn format_timestamp(timestamp: u64) -> String {
+    match chrono::DateTime::from_timestamp(timestamp as i64, 0) {
+        Some(time) => time.format("%Y-%m-%d %H:%M UTC").to_string(),
+        None => timestamp.to_string(),
+    }
+}
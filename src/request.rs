@@ -1,85 +1,222 @@
 use crate::config::{EndpointConfig, Method};
+use crate::feeder::Row;
+use crate::template::Templater;
 use anyhow::Result;
 use futures::Future;
-use reqwest::Client;
+use reqwest::{Client, RequestBuilder};
 use serde_json::Value;
 use std::{collections::HashMap, pin::Pin};
 
+/// Why a request didn't get the response it expected.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ErrorKind {
+    Timeout,
+    Connect,
+    Body,
+    Request,
+    Status,
+}
+
+impl ErrorKind {
+    fn from_reqwest(error: &reqwest::Error) -> Self {
+        if error.is_timeout() {
+            ErrorKind::Timeout
+        } else if error.is_connect() {
+            ErrorKind::Connect
+        } else if error.is_body() || error.is_decode() {
+            ErrorKind::Body
+        } else {
+            ErrorKind::Request
+        }
+    }
+}
+
+impl std::fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let kind = match self {
+            ErrorKind::Timeout => "timeout",
+            ErrorKind::Connect => "connect",
+            ErrorKind::Body => "body",
+            ErrorKind::Request => "request",
+            ErrorKind::Status => "status",
+        };
+        write!(f, "{}", kind)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct RequestOutput {
+    pub start: std::time::Instant,
     pub duration: u128,
-    pub success: bool,
     pub status: u16,
+    /// Size of the response bodies of every step.
+    pub bytes: u64,
+    pub error: Option<ErrorKind>,
     pub response_body: Option<Value>,
     pub response_headers: Option<HashMap<String, String>>,
 }
 
+impl RequestOutput {
+    /// The transport error of the request, or a status error when the status
+    /// isn't the expected one. Without an expected status any 4xx or 5xx
+    /// response is an error.
+    pub fn error_kind(&self, expected_status: Option<u16>) -> Option<ErrorKind> {
+        let unexpected_status = match expected_status {
+            Some(status) => self.status != status,
+            None => self.status >= 400,
+        };
+        match (self.error, unexpected_status) {
+            (Some(error), _) => Some(error),
+            (None, true) => Some(ErrorKind::Status),
+            (None, false) => None,
+        }
+    }
+}
+
 pub type Request = Pin<Box<dyn Future<Output = RequestOutput> + Send>>;
 
 pub struct TimedRequest {
     pub request: Request,
 }
 
-impl TimedRequest {
-    pub fn from_config(client: &Client, config: &EndpointConfig) -> Result<Self> {
-        let mut base_request = match config.method {
-            Method::Get => client.get(&config.url),
-            Method::Post => client.post(&config.url),
-            Method::Put => client.put(&config.url),
-            Method::Delete => client.delete(&config.url),
-            Method::Patch => client.patch(&config.url),
-            _ => {
-                return Err(anyhow::anyhow!("Invalid HTTP method: {}", config.method));
-            }
-        };
+fn build_request(
+    client: &Client,
+    method: &Method,
+    url: &str,
+    headers: Option<&HashMap<String, String>>,
+    body: Option<&Value>,
+    templater: &Templater,
+    row: Option<&Row>,
+) -> Result<RequestBuilder> {
+    let url = templater.render(url, row)?;
+    let mut base_request = match method {
+        Method::Get => client.get(&url),
+        Method::Post => client.post(&url),
+        Method::Put => client.put(&url),
+        Method::Delete => client.delete(&url),
+        Method::Patch => client.patch(&url),
+        _ => {
+            return Err(anyhow::anyhow!("Invalid HTTP method: {}", method));
+        }
+    };
 
-        if let Some(headers) = &config.headers {
-            for (key, value) in headers {
-                base_request = base_request.header(key, value);
-            }
+    if let Some(headers) = headers {
+        for (key, value) in headers {
+            base_request = base_request.header(key, templater.render(value, row)?);
         }
+    }
 
-        if let Some(body) = &config.body {
-            base_request = base_request.body(body.to_string());
+    if let Some(body) = body {
+        // String bodies are sent as is, so form and text payloads can be used too.
+        base_request = match templater.render_value(body, row)? {
+            Value::String(body) => base_request.body(body),
+            body => base_request.body(body.to_string()),
+        };
+    }
+
+    Ok(base_request)
+}
+
+impl TimedRequest {
+    pub fn from_config(
+        client: &Client,
+        config: &EndpointConfig,
+        templater: &Templater,
+        row: Option<&Row>,
+    ) -> Result<Self> {
+        let mut requests = vec![build_request(
+            client,
+            &config.method,
+            &config.url,
+            config.headers.as_ref(),
+            config.body.as_ref(),
+            templater,
+            row,
+        )?];
+
+        for step in config.steps.iter().flatten() {
+            requests.push(build_request(
+                client,
+                &step.method,
+                &step.url,
+                step.headers.as_ref(),
+                step.body.as_ref(),
+                templater,
+                row,
+            )?);
         }
 
+        // The requests of a scenario are sent one after another and timed as a
+        // whole, the first failing step ends the scenario early.
         let request = async move {
-            let start = std::time::Instant::now();
-            let response = base_request.send().await;
-            let duration = start.elapsed().as_millis();
-            match response {
-                Ok(r) => {
-                    let status = r.status().as_u16();
-                    let headers: Option<HashMap<String, String>> =
-                        match r.headers().iter().count() > 0 {
-                            true => {
-                                Some(HashMap::from_iter(r.headers().iter().map(|(k, v)| {
-                                    (k.to_string(), v.to_str().unwrap().to_string())
-                                })))
-                            }
-                            false => None,
-                        };
-                    let json: Option<Value> = r.json().await.unwrap_or(None);
-                    RequestOutput {
-                        duration,
-                        success: true,
-                        status,
-                        response_body: json,
-                        response_headers: headers,
-                    }
+            let started = std::time::Instant::now();
+            let num_requests = requests.len();
+            let mut duration = 0;
+            let mut bytes = 0;
+            let mut output = None;
+            for (i, base_request) in requests.into_iter().enumerate() {
+                let start = std::time::Instant::now();
+                let response = base_request.send().await;
+                duration += start.elapsed().as_millis();
+                let (step_output, is_failure) =
+                    match response {
+                        Ok(r) => {
+                            let status = r.status().as_u16();
+                            let headers: Option<HashMap<String, String>> =
+                                match r.headers().iter().count() > 0 {
+                                    true => Some(HashMap::from_iter(r.headers().iter().map(
+                                        |(k, v)| (k.to_string(), v.to_str().unwrap().to_string()),
+                                    ))),
+                                    false => None,
+                                };
+                            let (json, error) = match r.bytes().await {
+                                Ok(body) => {
+                                    bytes += body.len() as u64;
+                                    let json: Option<Value> =
+                                        serde_json::from_slice(&body).unwrap_or(None);
+                                    (json, None)
+                                }
+                                Err(e) => (None, Some(ErrorKind::from_reqwest(&e))),
+                            };
+                            let output = RequestOutput {
+                                start: started,
+                                duration: 0,
+                                status,
+                                bytes: 0,
+                                error,
+                                response_body: json,
+                                response_headers: headers,
+                            };
+                            (output, status >= 400 && i + 1 < num_requests)
+                        }
+                        Err(e) => {
+                            let output = RequestOutput {
+                                start: started,
+                                duration: 0,
+                                status: 0,
+                                bytes: 0,
+                                error: Some(ErrorKind::from_reqwest(&e)),
+                                response_body: None,
+                                response_headers: None,
+                            };
+                            (output, true)
+                        }
+                    };
+
+                output = Some(step_output);
+                if is_failure {
+                    break;
                 }
-                Err(_e) => RequestOutput {
-                    duration,
-                    success: false,
-                    status: 0,
-                    response_body: None,
-                    response_headers: None,
-                },
             }
+
+            let mut output = output.expect("a request has at least one step");
+            output.duration = duration;
+            output.bytes = bytes;
+            output
         };
 
-        return Ok(TimedRequest {
+        Ok(TimedRequest {
             request: Box::pin(request),
-        });
+        })
     }
 }
@@ -0,0 +1,117 @@
+use crate::config::RetentionConfig;
+use crate::snapshot::Snapshot;
+use crate::store::SnapshotStore;
+use anyhow::Result;
+use chrono::{DateTime, Datelike};
+use std::collections::HashSet;
+
+const DAY: u64 = 24 * 60 * 60;
+
+/// What pruning did or would do to the history.
+#[derive(Default)]
+pub struct Pruned {
+    pub removed: usize,
+    pub compacted: usize,
+}
+
+/// Keeps the most recent snapshot of each of the last `count` periods with
+/// snapshots, `period` maps a timestamp to the period it's in.
+fn keep_per_period<T: Eq + std::hash::Hash>(
+    snapshots: &[Snapshot],
+    keep: &mut [bool],
+    count: usize,
+    period: impl Fn(u64) -> T,
+) {
+    let mut seen = HashSet::new();
+    for (i, snapshot) in snapshots.iter().enumerate().rev() {
+        if seen.len() == count && !seen.contains(&period(snapshot.timestamp)) {
+            break;
+        }
+        if seen.insert(period(snapshot.timestamp)) {
+            keep[i] = true;
+        }
+    }
+}
+
+/// The snapshots `retention` keeps at `now`, with the samples of old ones
+/// dropped, oldest first.
+pub fn apply(
+    mut snapshots: Vec<Snapshot>,
+    retention: &RetentionConfig,
+    now: u64,
+    pruned: &mut Pruned,
+) -> Vec<Snapshot> {
+    snapshots.sort_by_key(|s| s.timestamp);
+    let has_rules = retention.keep_last.is_some()
+        || retention.keep_days.is_some()
+        || retention.keep_weekly.is_some()
+        || retention.keep_monthly.is_some();
+
+    let mut keep = snapshots
+        .iter()
+        .map(|s| !has_rules || s.pinned)
+        .collect::<Vec<_>>();
+    if let Some(last) = retention.keep_last {
+        let len = keep.len();
+        keep.iter_mut()
+            .skip(len.saturating_sub(last))
+            .for_each(|k| *k = true);
+    }
+    if let Some(days) = retention.keep_days {
+        for (i, snapshot) in snapshots.iter().enumerate() {
+            keep[i] |= now.saturating_sub(snapshot.timestamp) <= days * DAY;
+        }
+    }
+    let date = |timestamp: u64| DateTime::from_timestamp(timestamp as i64, 0).unwrap_or_default();
+    if let Some(weeks) = retention.keep_weekly {
+        keep_per_period(&snapshots, &mut keep, weeks, |t| {
+            let week = date(t).iso_week();
+            (week.year(), week.week())
+        });
+    }
+    if let Some(months) = retention.keep_monthly {
+        keep_per_period(&snapshots, &mut keep, months, |t| {
+            (date(t).year(), date(t).month())
+        });
+    }
+
+    let mut kept = vec![];
+    for (snapshot, keep) in snapshots.into_iter().zip(keep) {
+        if !keep {
+            pruned.removed += 1;
+            continue;
+        }
+        let mut snapshot = snapshot;
+        let compact = retention
+            .compact_after_days
+            .is_some_and(|days| now.saturating_sub(snapshot.timestamp) > days * DAY);
+        if compact && snapshot.tests.iter().any(|t| t.samples.is_some()) {
+            snapshot.tests.iter_mut().for_each(|t| t.samples = None);
+            pruned.compacted += 1;
+        }
+        kept.push(snapshot);
+    }
+
+    kept
+}
+
+/// Applies `retention` to the snapshots in `store`, or only counts what it
+/// would do with `dry_run`.
+pub fn prune(
+    store: &dyn SnapshotStore,
+    retention: &RetentionConfig,
+    now: u64,
+    dry_run: bool,
+) -> Result<Pruned> {
+    // Pruning runs after every write, the store is only rewritten when
+    // there's something to prune.
+    let mut planned = Pruned::default();
+    apply(store.read()?, retention, now, &mut planned);
+    if dry_run || planned.removed + planned.compacted == 0 {
+        return Ok(planned);
+    }
+
+    let mut pruned = Pruned::default();
+    store.update(&mut |snapshots| apply(snapshots, retention, now, &mut pruned))?;
+    Ok(pruned)
+}
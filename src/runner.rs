@@ -1,17 +1,28 @@
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use futures::future::join_all;
-use std::vec;
+use std::collections::HashMap;
+use std::sync::Arc;
 use tokio::time;
 use tokio::time::Duration;
 use tokio_stream::{self as stream, StreamExt};
 
 use crate::config::{Config, EndpointConfig};
+use crate::dashboard::{Dashboard, LiveStats};
+use crate::feeder::{Feeder, Row};
+use crate::metrics::Metrics;
 use crate::printer::Printer;
 use crate::request::{Request, RequestOutput, TimedRequest};
+use crate::sink::Sinks;
+use crate::template::Templater;
 
 pub struct Runner {
     config: Config,
     client: reqwest::Client,
+    templater: Templater,
+    feeders: HashMap<String, Feeder>,
+    metrics: Option<Arc<Metrics>>,
+    sinks: Option<Arc<Sinks>>,
+    repeat: u64,
 }
 
 pub type SingleCycle = Vec<RequestOutput>;
@@ -23,24 +34,83 @@ pub struct SingleLoad {
     pub num_concurrent_requests: u64,
     pub endpoint_name: String,
     pub endpoint_url: String,
+    /// The number of cycles of each run when the load is repeated, the
+    /// cycles of all runs are in `cycles` one after the other.
+    pub runs: Vec<usize>,
 }
 
 pub type Loads = Vec<SingleLoad>;
 
 impl Runner {
-    pub fn new(config: Config) -> Self {
+    pub fn new(config: Config) -> Result<Self> {
         let client = reqwest::Client::new();
-        Self { config, client }
+        let templater = Templater::new(config.variables.clone().unwrap_or_default());
+        let mut feeders = HashMap::new();
+        for endpoint in &config.endpoints {
+            if let Some(data) = &endpoint.data {
+                let feeder = Feeder::from_config(data, endpoint.concurrent_requests)?;
+                feeders.insert(endpoint.name.clone(), feeder);
+            }
+        }
+
+        Ok(Self {
+            config,
+            client,
+            templater,
+            feeders,
+            metrics: None,
+            sinks: None,
+            repeat: 1,
+        })
+    }
+
+    /// Records every request of the load in `metrics`.
+    pub fn with_metrics(mut self, metrics: Arc<Metrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
     }
 
-    fn make_request(&self, endpoint: &EndpointConfig) -> Result<Request> {
-        Ok(TimedRequest::from_config(&self.client, &endpoint)?.request)
+    /// Passes every request of the load to `sinks`.
+    pub fn with_sinks(mut self, sinks: Arc<Sinks>) -> Self {
+        self.sinks = Some(sinks);
+        self
     }
 
-    fn make_cycle(&self, endpoint: &EndpointConfig) -> Result<Vec<Request>> {
-        Ok((0..endpoint.concurrent_requests)
-            .map(|_| self.make_request(endpoint).unwrap())
-            .collect::<Vec<_>>())
+    /// Runs the load of every endpoint `repeat` times in a row, to tell how
+    /// much its response times vary between runs.
+    pub fn with_repeat(mut self, repeat: u64) -> Self {
+        self.repeat = repeat.max(1);
+        self
+    }
+
+    fn make_request(&self, endpoint: &EndpointConfig, row: Option<&Row>) -> Result<Request> {
+        Ok(TimedRequest::from_config(&self.client, endpoint, &self.templater, row)?.request)
+    }
+
+    /// Builds the requests of a single cycle, this can return less than
+    /// `num_requests` requests when the endpoint ran out of data.
+    fn make_cycle(
+        &self,
+        endpoint: &EndpointConfig,
+        num_requests: u64,
+        warm_up: bool,
+    ) -> Result<Vec<Request>> {
+        let feeder = self.feeders.get(&endpoint.name);
+        let mut cycle = vec![];
+        for virtual_user in 0..num_requests {
+            // Warm up requests shouldn't use up any of the rows of the actual load.
+            let row = match (feeder, warm_up) {
+                (None, _) => None,
+                (Some(feeder), true) => Some(feeder.sample()),
+                (Some(feeder), false) => match feeder.next(virtual_user)? {
+                    Some(row) => Some(row),
+                    None => break,
+                },
+            };
+            cycle.push(self.make_request(endpoint, row)?);
+        }
+
+        Ok(cycle)
     }
 
     async fn log_ramp(&self, endpoint: &EndpointConfig) -> Result<()> {
@@ -50,26 +120,22 @@ impl Runner {
         let ramp = (0..max_count)
             .map(|i| {
                 let curr_scale = i as f64 / max_count as f64 * log_scale;
-                let num_requests = curr_scale.exp().ceil() as usize;
-                let cycle = self.make_cycle(endpoint).unwrap();
-                let cycle = cycle.into_iter().take(num_requests).collect::<Vec<_>>();
-                cycle
+                let num_requests = curr_scale.exp().ceil() as u64;
+                self.make_cycle(
+                    endpoint,
+                    num_requests.min(endpoint.concurrent_requests),
+                    true,
+                )
             })
-            .collect::<Vec<_>>();
+            .collect::<Result<Vec<_>>>()?;
 
-        let cycles: Vec<_> = ramp
-            .into_iter()
-            .map(|cycle| join_all(cycle.into_iter().map(|req| req)))
-            .collect::<Vec<_>>();
+        let cycles: Vec<_> = ramp.into_iter().map(join_all).collect::<Vec<_>>();
 
         stream::iter(cycles)
             .then(|cycle| async move {
                 let cycle_results = cycle.await;
                 time::sleep(Duration::from_millis(100)).await;
                 cycle_results
-                    .into_iter()
-                    .map(|res| res.clone())
-                    .collect::<Vec<_>>()
             })
             .collect::<Vec<_>>()
             .await;
@@ -77,54 +143,109 @@ impl Runner {
         Ok(())
     }
 
+    /// Runs one load of `endpoint`, `None` when it has no data left.
+    async fn run_load(
+        &self,
+        endpoint: &EndpointConfig,
+        printer: &Printer,
+    ) -> Result<Option<Vec<SingleCycle>>> {
+        let mut raw_cycles = vec![];
+        for _ in 0..endpoint.cycles {
+            let cycle = self.make_cycle(endpoint, endpoint.concurrent_requests, false)?;
+            if cycle.is_empty() {
+                break;
+            }
+            raw_cycles.push(cycle);
+        }
+
+        if raw_cycles.is_empty() {
+            return Ok(None);
+        }
+
+        let total = raw_cycles.iter().map(|c| c.len() as u64).sum();
+        let live = LiveStats::new(total, endpoint.expected_status);
+        let cycles: Vec<_> = raw_cycles
+            .into_iter()
+            .map(|cycle| {
+                join_all(cycle.into_iter().map(|request| {
+                    let request = live.track(request);
+                    let request = match &self.metrics {
+                        Some(metrics) => metrics.track(endpoint, request),
+                        None => request,
+                    };
+                    match &self.sinks {
+                        Some(sinks) => sinks.track(endpoint, request),
+                        None => request,
+                    }
+                }))
+            })
+            .collect::<Vec<_>>();
+
+        let dashboard = Dashboard::start(printer.clone(), endpoint.name.clone(), live);
+        let results = stream::iter(cycles)
+            .then(|cycle| async move {
+                let cycle_results = cycle.await;
+                time::sleep(Duration::from_millis(100)).await;
+                cycle_results
+            })
+            .collect::<Vec<_>>()
+            .await;
+        dashboard.stop().await;
+
+        Ok(Some(results))
+    }
+
     pub async fn run(&self, printer: &Printer) -> Result<Loads> {
         let mut loads: Loads = vec![];
         for endpoint in &self.config.endpoints {
-            let should_ramp = match endpoint.ramp {
-                Some(false) => false,
-                _ => true,
-            };
+            let should_ramp = !matches!(endpoint.ramp, Some(false));
             if should_ramp {
-                printer.print_with_yellow("Warming", &format!("up {} with a logarithmic ramp", endpoint.name), 4);
-                self.log_ramp(&endpoint).await?;
-                printer.clear_previous()
-                    .print_with_green("Warmed", &format!("up {} with a logarithmic ramp", endpoint.name), 4);
+                printer.print_with_yellow(
+                    "Warming",
+                    &format!("up {} with a logarithmic ramp", endpoint.name),
+                    4,
+                );
+                self.log_ramp(endpoint).await?;
+                printer.clear_previous().print_with_green(
+                    "Warmed",
+                    &format!("up {} with a logarithmic ramp", endpoint.name),
+                    4,
+                );
+            }
+
+            let mut cycles = vec![];
+            let mut runs = vec![];
+            for run in 1..=self.repeat {
+                let load = match self.repeat {
+                    1 => format!("load for {}", endpoint.name),
+                    repeat => format!("load for {} ({} of {})", endpoint.name, run, repeat),
+                };
+                printer.print_with_yellow("Running", &load, 4);
+                let results = match self.run_load(endpoint, printer).await? {
+                    Some(results) => results,
+                    None if runs.is_empty() => {
+                        return Err(anyhow!("No data left to run load for {}", endpoint.name))
+                    }
+                    // The data ran out, the runs so far are all there is.
+                    None => {
+                        printer.clear_previous();
+                        break;
+                    }
+                };
+                runs.push(results.len());
+                cycles.extend(results);
+                printer.clear_previous();
+                printer.print_with_green("Finished", &load, 4);
             }
-            printer.print_with_yellow("Running", &format!("load for {}", endpoint.name), 4);
-            let num_cycles = endpoint.cycles.clone();
-            let num_concurrent_requests = endpoint.concurrent_requests.clone();
-
-            let raw_cycles = (0..num_cycles)
-                .map(|_| self.make_cycle(&endpoint).unwrap())
-                .collect::<Vec<_>>();
-
-            let cycles: Vec<_> = raw_cycles
-                .into_iter()
-                .map(|cycle| join_all(cycle.into_iter().map(|req| req)))
-                .collect::<Vec<_>>();
-
-            let results = stream::iter(cycles)
-                .then(|cycle| async move {
-                    let cycle_results = cycle.await;
-                    time::sleep(Duration::from_millis(100)).await;
-                    cycle_results
-                        .into_iter()
-                        .map(|res| res.clone())
-                        .collect::<Vec<_>>()
-                })
-                .collect::<Vec<_>>()
-                .await;
 
             loads.push(SingleLoad {
-                cycles: results,
-                num_cycles,
-                num_concurrent_requests,
+                num_cycles: cycles.len() as u64,
+                cycles,
+                num_concurrent_requests: endpoint.concurrent_requests,
                 endpoint_name: endpoint.name.clone(),
                 endpoint_url: endpoint.url.clone(),
+                runs,
             });
-
-            printer.clear_previous();
-            printer.print_with_green("Finished", &format!("load for {}", endpoint.name), 4);
         }
 
         Ok(loads)
@@ -0,0 +1,103 @@
+use crate::config::{Config, DataFormat};
+use crate::runner::Loads;
+use anyhow::{anyhow, Context, Result};
+use serde::Serialize;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::str::FromStr;
+
+/// A single request of the run, as written to the samples file.
+#[derive(Serialize)]
+struct SampleRecord<'a> {
+    /// Milliseconds since the first request of the run started.
+    offset: u64,
+    endpoint: &'a str,
+    cycle: usize,
+    latency: u128,
+    status: u16,
+    bytes: u64,
+    error: Option<String>,
+}
+
+/// The file every request of the run is written to, the format is inferred
+/// from the extension of the path.
+#[derive(Debug, Clone)]
+pub struct SamplesTarget {
+    pub format: DataFormat,
+    pub path: String,
+}
+
+impl FromStr for SamplesTarget {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let format = match s.rsplit('.').next() {
+            Some("csv") => DataFormat::Csv,
+            Some("jsonl") | Some("ndjson") => DataFormat::Jsonl,
+            _ => return Err(anyhow!("expected a .csv or .jsonl file")),
+        };
+
+        Ok(Self {
+            format,
+            path: s.to_string(),
+        })
+    }
+}
+
+/// Writes every request of the run to the samples file. Returns the number of
+/// samples written.
+pub fn write_samples(target: &SamplesTarget, loads: &Loads, config: &Config) -> Result<usize> {
+    let file =
+        File::create(&target.path).with_context(|| format!("Failed to create {}", target.path))?;
+
+    let run_start = loads
+        .iter()
+        .flat_map(|load| load.cycles.iter().flatten())
+        .map(|output| output.start)
+        .min();
+
+    let mut records = vec![];
+    for load in loads {
+        let expected_status = config
+            .endpoints
+            .iter()
+            .find(|e| e.name == load.endpoint_name)
+            .and_then(|e| e.expected_status);
+
+        for (cycle, outputs) in load.cycles.iter().enumerate() {
+            for output in outputs {
+                records.push(SampleRecord {
+                    offset: run_start
+                        .map(|start| output.start.duration_since(start).as_millis() as u64)
+                        .unwrap_or(0),
+                    endpoint: &load.endpoint_name,
+                    cycle,
+                    latency: output.duration,
+                    status: output.status,
+                    bytes: output.bytes,
+                    error: output.error_kind(expected_status).map(|e| e.to_string()),
+                });
+            }
+        }
+    }
+
+    match target.format {
+        DataFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(file);
+            for record in &records {
+                writer.serialize(record)?;
+            }
+            writer.flush()?;
+        }
+        DataFormat::Jsonl => {
+            let mut writer = BufWriter::new(file);
+            for record in &records {
+                serde_json::to_writer(&mut writer, record)?;
+                writer.write_all(b"\n")?;
+            }
+            writer.flush()?;
+        }
+    }
+
+    Ok(records.len())
+}
@@ -0,0 +1,88 @@
+use serde_json::Value;
+
+/// Checks a response body against the subset of json schema used by OpenAPI
+/// response definitions: `type`, `nullable`, `enum`, `required`, `properties`,
+/// `additionalProperties`, `items`, `allOf`, `anyOf` and `oneOf`.
+///
+/// Schemas are expected to have their `$ref`s resolved when they are imported,
+/// unknown keywords are ignored.
+pub fn validate(schema: &Value, value: &Value) -> bool {
+    let schema = match schema.as_object() {
+        Some(schema) => schema,
+        None => return true,
+    };
+
+    if value.is_null() && schema.get("nullable") == Some(&Value::Bool(true)) {
+        return true;
+    }
+
+    if let Some(Value::Array(options)) = schema.get("enum") {
+        if !options.contains(value) {
+            return false;
+        }
+    }
+
+    if let Some(Value::Array(schemas)) = schema.get("allOf") {
+        if !schemas.iter().all(|s| validate(s, value)) {
+            return false;
+        }
+    }
+
+    for keyword in ["anyOf", "oneOf"] {
+        if let Some(Value::Array(schemas)) = schema.get(keyword) {
+            if !schemas.iter().any(|s| validate(s, value)) {
+                return false;
+            }
+        }
+    }
+
+    let type_matches = match schema.get("type").and_then(|t| t.as_str()) {
+        Some("object") => value.is_object(),
+        Some("array") => value.is_array(),
+        Some("string") => value.is_string(),
+        Some("integer") => value.is_i64() || value.is_u64(),
+        Some("number") => value.is_number(),
+        Some("boolean") => value.is_boolean(),
+        Some("null") => value.is_null(),
+        _ => true,
+    };
+    if !type_matches {
+        return false;
+    }
+
+    if let Value::Object(object) = value {
+        if let Some(Value::Array(required)) = schema.get("required") {
+            let has_required = required
+                .iter()
+                .filter_map(|r| r.as_str())
+                .all(|r| object.contains_key(r));
+            if !has_required {
+                return false;
+            }
+        }
+
+        let properties = schema.get("properties").and_then(|p| p.as_object());
+        for (key, property) in object {
+            let valid = match (
+                properties.and_then(|p| p.get(key)),
+                schema.get("additionalProperties"),
+            ) {
+                (Some(property_schema), _) => validate(property_schema, property),
+                (None, Some(Value::Bool(false))) => false,
+                (None, Some(additional @ Value::Object(_))) => validate(additional, property),
+                (None, _) => true,
+            };
+            if !valid {
+                return false;
+            }
+        }
+    }
+
+    if let (Value::Array(items), Some(item_schema)) = (value, schema.get("items")) {
+        if !items.iter().all(|item| validate(item_schema, item)) {
+            return false;
+        }
+    }
+
+    true
+}
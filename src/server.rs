@@ -0,0 +1,186 @@
+use crate::config::{ReadyCheck, ServerConfig};
+use anyhow::{anyhow, Context, Result};
+use std::fs::File;
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+use std::time::{Duration, Instant};
+
+/// How long a server without a readiness check is given to start.
+pub const STARTUP_WAIT: Duration = Duration::from_secs(5);
+
+/// A shell command, run with `sh -c` (or `cmd /C` on windows).
+pub fn shell(command: &str) -> Command {
+    match cfg!(windows) {
+        true => {
+            let mut shell = Command::new("cmd");
+            shell.args(["/C", command]);
+            shell
+        }
+        false => {
+            let mut shell = Command::new("sh");
+            shell.args(["-c", command]);
+            shell
+        }
+    }
+}
+
+/// The service under test, started from a shell command and stopped when
+/// dropped.
+pub struct Server {
+    child: Option<Child>,
+}
+
+/// Runs the build command of `config` in its dir, failing with the last line
+/// of its output when it fails.
+pub fn build(config: &ServerConfig) -> Result<()> {
+    let command = match &config.build {
+        Some(command) => command,
+        None => return Ok(()),
+    };
+    let mut build = shell(command);
+    build.current_dir(config.dir.as_deref().unwrap_or("."));
+    if let Some(env) = &config.env {
+        build.envs(env);
+    }
+    let output = build
+        .output()
+        .with_context(|| format!("Failed to run {}", command))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!(
+            "{} failed with {}: {}",
+            command,
+            output.status,
+            stderr.lines().last().unwrap_or_default()
+        ));
+    }
+
+    Ok(())
+}
+
+impl Server {
+    pub fn start(command: &str, dir: &Path) -> Result<Self> {
+        let mut shell = shell(command);
+        shell
+            .current_dir(dir)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null());
+        Self::spawn(shell, command)
+    }
+
+    /// Starts the server of `config`, with its env and its output written to
+    /// its log file.
+    pub fn from_config(config: &ServerConfig) -> Result<Self> {
+        let mut shell = shell(&config.command);
+        shell.current_dir(config.dir.as_deref().unwrap_or("."));
+        if let Some(env) = &config.env {
+            shell.envs(env);
+        }
+        match &config.log {
+            Some(path) => {
+                let log =
+                    File::create(path).with_context(|| format!("Failed to create {}", path))?;
+                let stderr = log
+                    .try_clone()
+                    .with_context(|| format!("Failed to open {}", path))?;
+                shell.stdout(log).stderr(stderr);
+            }
+            None => {
+                shell.stdout(Stdio::null()).stderr(Stdio::null());
+            }
+        }
+        Self::spawn(shell, &config.command)
+    }
+
+    fn spawn(mut shell: Command, command: &str) -> Result<Self> {
+        shell.stdin(Stdio::null());
+        // The command is usually a wrapper (a shell, cargo run, npm start...)
+        // around the actual server, a group of its own lets both be stopped.
+        #[cfg(unix)]
+        std::os::unix::process::CommandExt::process_group(&mut shell, 0);
+
+        let child = shell
+            .spawn()
+            .with_context(|| format!("Failed to start {}", command))?;
+        Ok(Self { child: Some(child) })
+    }
+
+    fn exited(&mut self) -> Option<std::process::ExitStatus> {
+        self.child
+            .as_mut()
+            .and_then(|child| child.try_wait().ok().flatten())
+    }
+
+    /// Waits until `url` answers with any response, failing when the server
+    /// exits or doesn't answer within `timeout`.
+    pub async fn wait_ready(&mut self, url: &str, timeout: Duration) -> Result<()> {
+        let check = ReadyCheck::Http {
+            url: url.to_string(),
+        };
+        self.wait_for(&check, timeout).await
+    }
+
+    /// Waits until `check` passes, failing when the server exits or it
+    /// doesn't pass within `timeout`.
+    pub async fn wait_for(&mut self, check: &ReadyCheck, timeout: Duration) -> Result<()> {
+        let client = reqwest::Client::new();
+        let started = Instant::now();
+        loop {
+            if let Some(status) = self.exited() {
+                return Err(anyhow!(
+                    "The server exited with {} before it was ready",
+                    status
+                ));
+            }
+            let ready = match check {
+                ReadyCheck::Http { url } => client.get(url).send().await.is_ok(),
+                ReadyCheck::Tcp { .. } => tokio::net::TcpStream::connect(check.to_string())
+                    .await
+                    .is_ok(),
+            };
+            if ready {
+                return Ok(());
+            }
+            if started.elapsed() > timeout {
+                return Err(anyhow!(
+                    "The server wasn't ready at {} after {}s",
+                    check,
+                    timeout.as_secs()
+                ));
+            }
+            tokio::time::sleep(Duration::from_millis(250)).await;
+        }
+    }
+
+    /// Asks the server to stop, and kills it if it's still running after a
+    /// few seconds.
+    pub fn stop(&mut self) {
+        let mut child = match self.child.take() {
+            Some(child) => child,
+            None => return,
+        };
+
+        #[cfg(unix)]
+        {
+            let group = format!("-{}", child.id());
+            let _ = Command::new("kill").args(["-TERM", &group]).status();
+            let started = Instant::now();
+            while started.elapsed() < Duration::from_secs(5) {
+                if let Ok(Some(_)) = child.try_wait() {
+                    return;
+                }
+                std::thread::sleep(Duration::from_millis(50));
+            }
+            let _ = Command::new("kill").args(["-KILL", &group]).status();
+        }
+
+        let _ = child.kill();
+        let _ = child.wait();
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
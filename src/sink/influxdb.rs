@@ -0,0 +1,124 @@
+use super::{post, Aggregate, Finished, ResultSink};
+use anyhow::Result;
+use futures::future::BoxFuture;
+use std::collections::HashMap;
+use std::time::Duration;
+
+// Commas, equal signs and spaces separate the parts of a line.
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace(',', "\\,")
+        .replace('=', "\\=")
+        .replace(' ', "\\ ")
+}
+
+/// Posts the aggregates of every interval and the stats of every test to an
+/// influxdb write url as line protocol, with nanosecond timestamps.
+pub struct InfluxDb {
+    client: reqwest::Client,
+    url: String,
+    headers: HashMap<String, String>,
+    interval: Duration,
+    desc: Option<String>,
+}
+
+impl InfluxDb {
+    pub fn new(
+        url: String,
+        headers: HashMap<String, String>,
+        interval: Duration,
+        desc: Option<String>,
+    ) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            url,
+            headers,
+            interval,
+            desc,
+        }
+    }
+
+    fn tags(&self, endpoint: &str, method: &str) -> String {
+        let mut tags = format!("endpoint={},method={}", escape(endpoint), escape(method));
+        if let Some(desc) = &self.desc {
+            tags.push_str(&format!(",desc={}", escape(desc)));
+        }
+        tags
+    }
+}
+
+impl ResultSink for InfluxDb {
+    fn name(&self) -> &'static str {
+        "influxdb"
+    }
+
+    fn interval(&self) -> Option<Duration> {
+        Some(self.interval)
+    }
+
+    fn write_interval<'a>(&'a self, aggregates: &'a [Aggregate]) -> BoxFuture<'a, Result<()>> {
+        let lines = aggregates
+            .iter()
+            .map(|a| {
+                format!(
+                    "ballast_requests,{} count={}i,errors={}i,bytes={}i,mean={},p50={},p99={},max={} {}",
+                    self.tags(&a.endpoint, &a.method),
+                    a.count,
+                    a.errors,
+                    a.bytes,
+                    a.mean,
+                    a.p50,
+                    a.p99,
+                    a.max,
+                    a.timestamp * 1_000_000
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        Box::pin(post(
+            &self.client,
+            &self.url,
+            &self.headers,
+            "text/plain; charset=utf-8",
+            lines,
+        ))
+    }
+
+    fn finish<'a>(&'a self, run: &'a Finished<'a>) -> BoxFuture<'a, Result<()>> {
+        let lines = run
+            .snapshot
+            .tests
+            .iter()
+            .map(|test| {
+                let method = run
+                    .config
+                    .endpoints
+                    .iter()
+                    .find(|e| e.name == test.config.endpoint_name)
+                    .map(|e| e.method.to_string())
+                    .unwrap_or_default();
+                format!(
+                    "ballast_tests,{} success={},within_threshold={},average={},min={},max={} {}",
+                    self.tags(&test.config.endpoint_name, &method),
+                    test.success,
+                    test.within_threshold,
+                    test.stats.average_response_time,
+                    test.stats.min_response_time,
+                    test.stats.max_response_time,
+                    run.snapshot.timestamp * 1_000_000_000
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        Box::pin(post(
+            &self.client,
+            &self.url,
+            &self.headers,
+            "text/plain; charset=utf-8",
+            lines,
+        ))
+    }
+}
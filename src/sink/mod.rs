@@ -0,0 +1,320 @@
+pub mod influxdb;
+pub mod statsd;
+pub mod webhook;
+
+use crate::config::{Config, EndpointConfig, SinkConfig};
+use crate::process::percentile;
+use crate::request::{Request, RequestOutput};
+use crate::snapshot::Snapshot;
+use crate::template::Templater;
+use anyhow::{Context, Result};
+use futures::future::BoxFuture;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+
+/// A finished request, passed to every sink as soon as it's done.
+pub struct Event<'a> {
+    pub endpoint: &'a EndpointConfig,
+    pub output: &'a RequestOutput,
+}
+
+/// The requests of an endpoint that finished within one interval.
+#[derive(Debug, Clone)]
+pub struct Aggregate {
+    pub endpoint: String,
+    pub method: String,
+    /// Unix timestamp in milliseconds of the end of the interval.
+    pub timestamp: u64,
+    pub count: u64,
+    pub errors: u64,
+    pub bytes: u64,
+    pub mean: f64,
+    pub p50: f64,
+    pub p99: f64,
+    pub max: f64,
+}
+
+/// A run once it's processed, passed to every sink at the end.
+pub struct Finished<'a> {
+    pub snapshot: &'a Snapshot,
+    pub config: &'a Config,
+    pub latest: Option<&'a Snapshot>,
+}
+
+/// Somewhere the results of a run are sent while it runs. A sink can take
+/// every request as it finishes, aggregates of the requests every interval,
+/// and the processed run at the end.
+pub trait ResultSink: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// How often `write_interval` is called, sinks without one don't get
+    /// aggregates.
+    fn interval(&self) -> Option<Duration> {
+        None
+    }
+
+    fn record(&self, _event: &Event) -> Result<()> {
+        Ok(())
+    }
+
+    fn write_interval<'a>(&'a self, _aggregates: &'a [Aggregate]) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async { Ok(()) })
+    }
+
+    fn finish<'a>(&'a self, _run: &'a Finished<'a>) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async { Ok(()) })
+    }
+}
+
+pub fn unix_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// Posts `body` to `url`, failing on any non 2xx response.
+pub async fn post(
+    client: &reqwest::Client,
+    url: &str,
+    headers: &HashMap<String, String>,
+    content_type: &str,
+    body: String,
+) -> Result<()> {
+    let mut request = client
+        .post(url)
+        .header("Content-Type", content_type)
+        .body(body);
+    for (key, value) in headers {
+        request = request.header(key, value);
+    }
+    request
+        .send()
+        .await
+        .with_context(|| format!("Failed to post to {}", url))?
+        .error_for_status()?;
+    Ok(())
+}
+
+struct Buffered {
+    endpoint: String,
+    method: String,
+    duration: u128,
+    bytes: u64,
+    is_error: bool,
+}
+
+struct SinkState {
+    sink: Box<dyn ResultSink>,
+    buffer: Mutex<Vec<Buffered>>,
+    // A sink failing shouldn't stop the load, the last error is reported at
+    // the end of the run instead.
+    last_error: Mutex<Option<anyhow::Error>>,
+}
+
+impl SinkState {
+    fn fail(&self, error: anyhow::Error) {
+        *self.last_error.lock().unwrap() = Some(error);
+    }
+
+    async fn flush(&self) {
+        let buffered = std::mem::take(&mut *self.buffer.lock().unwrap());
+        if buffered.is_empty() {
+            return;
+        }
+
+        let timestamp = unix_millis();
+        let mut aggregates: Vec<(Aggregate, Vec<f64>)> = vec![];
+        for request in buffered {
+            let index = match aggregates
+                .iter()
+                .position(|(a, _)| a.endpoint == request.endpoint)
+            {
+                Some(index) => index,
+                None => {
+                    aggregates.push((
+                        Aggregate {
+                            endpoint: request.endpoint.clone(),
+                            method: request.method.clone(),
+                            timestamp,
+                            count: 0,
+                            errors: 0,
+                            bytes: 0,
+                            mean: 0.0,
+                            p50: 0.0,
+                            p99: 0.0,
+                            max: 0.0,
+                        },
+                        vec![],
+                    ));
+                    aggregates.len() - 1
+                }
+            };
+            let (aggregate, durations) = &mut aggregates[index];
+            aggregate.count += 1;
+            aggregate.errors += request.is_error as u64;
+            aggregate.bytes += request.bytes;
+            durations.push(request.duration as f64);
+        }
+
+        let aggregates = aggregates
+            .into_iter()
+            .map(|(mut aggregate, mut durations)| {
+                durations.sort_by(|a, b| a.partial_cmp(b).unwrap());
+                aggregate.mean = durations.iter().sum::<f64>() / durations.len() as f64;
+                aggregate.p50 = percentile(&durations, 50.0);
+                aggregate.p99 = percentile(&durations, 99.0);
+                aggregate.max = durations.last().cloned().unwrap_or(0.0);
+                aggregate
+            })
+            .collect::<Vec<_>>();
+
+        if let Err(e) = self.sink.write_interval(&aggregates).await {
+            self.fail(e);
+        }
+    }
+}
+
+/// The sinks of a run, fed by the runner.
+pub struct Sinks {
+    sinks: Vec<Arc<SinkState>>,
+    tickers: Mutex<Vec<(oneshot::Sender<()>, JoinHandle<()>)>>,
+}
+
+impl Sinks {
+    /// Sets up the sinks in the config and starts the interval of each, returns
+    /// `None` without any sinks.
+    pub fn start(config: &Config, desc: Option<String>) -> Result<Option<Arc<Self>>> {
+        let configs = match &config.sinks {
+            Some(configs) if !configs.is_empty() => configs,
+            _ => return Ok(None),
+        };
+
+        // Header values can use variables, e.g. to read a token from the env.
+        let templater = Templater::new(config.variables.clone().unwrap_or_default());
+        let render_headers = |headers: &Option<HashMap<String, String>>| {
+            headers
+                .iter()
+                .flatten()
+                .map(|(k, v)| Ok((k.clone(), templater.render(v, None)?)))
+                .collect::<Result<HashMap<_, _>>>()
+        };
+
+        let mut sinks = vec![];
+        for sink_config in configs {
+            let sink: Box<dyn ResultSink> = match sink_config {
+                SinkConfig::Influxdb {
+                    url,
+                    headers,
+                    interval,
+                } => Box::new(influxdb::InfluxDb::new(
+                    templater.render(url, None)?,
+                    render_headers(headers)?,
+                    Duration::from_secs(interval.unwrap_or(10).max(1)),
+                    desc.clone(),
+                )),
+                SinkConfig::Statsd {
+                    addr,
+                    prefix,
+                    dogstatsd,
+                } => Box::new(statsd::Statsd::new(
+                    &templater.render(addr, None)?,
+                    prefix.clone().unwrap_or_else(|| "ballast".to_string()),
+                    dogstatsd.unwrap_or(false),
+                    desc.clone(),
+                )?),
+                SinkConfig::Webhook { url, headers } => Box::new(webhook::Webhook::new(
+                    templater.render(url, None)?,
+                    render_headers(headers)?,
+                )),
+            };
+            sinks.push(Arc::new(SinkState {
+                sink,
+                buffer: Mutex::new(vec![]),
+                last_error: Mutex::new(None),
+            }));
+        }
+
+        let mut tickers = vec![];
+        for state in &sinks {
+            if let Some(interval) = state.sink.interval() {
+                let state = state.clone();
+                let (stop, mut stopped) = oneshot::channel::<()>();
+                let handle = tokio::spawn(async move {
+                    let mut ticker = tokio::time::interval(interval);
+                    // The first tick completes right away.
+                    ticker.tick().await;
+                    loop {
+                        tokio::select! {
+                            _ = &mut stopped => break,
+                            _ = ticker.tick() => state.flush().await,
+                        }
+                    }
+                });
+                tickers.push((stop, handle));
+            }
+        }
+
+        Ok(Some(Arc::new(Self {
+            sinks,
+            tickers: Mutex::new(tickers),
+        })))
+    }
+
+    /// Wraps a request of `endpoint` so it's passed to the sinks once it
+    /// finishes.
+    pub fn track(self: &Arc<Self>, endpoint: &EndpointConfig, request: Request) -> Request {
+        let sinks = self.clone();
+        let endpoint = endpoint.clone();
+        Box::pin(async move {
+            let output = request.await;
+            let event = Event {
+                endpoint: &endpoint,
+                output: &output,
+            };
+            for state in &sinks.sinks {
+                if let Err(e) = state.sink.record(&event) {
+                    state.fail(e);
+                }
+                if state.sink.interval().is_some() {
+                    state.buffer.lock().unwrap().push(Buffered {
+                        endpoint: endpoint.name.clone(),
+                        method: endpoint.method.to_string(),
+                        duration: output.duration,
+                        bytes: output.bytes,
+                        is_error: output.error_kind(endpoint.expected_status).is_some(),
+                    });
+                }
+            }
+            output
+        })
+    }
+
+    /// Stops the intervals, sends what's left and the processed run to every
+    /// sink. Returns the sinks that failed at any point with their last error.
+    pub async fn finish(&self, run: &Finished<'_>) -> Vec<(&'static str, anyhow::Error)> {
+        let tickers = std::mem::take(&mut *self.tickers.lock().unwrap());
+        for (stop, handle) in tickers {
+            let _ = stop.send(());
+            let _ = handle.await;
+        }
+
+        let mut errors = vec![];
+        for state in &self.sinks {
+            if state.sink.interval().is_some() {
+                state.flush().await;
+            }
+            if let Err(e) = state.sink.finish(run).await {
+                state.fail(e);
+            }
+            if let Some(e) = state.last_error.lock().unwrap().take() {
+                errors.push((state.sink.name(), e));
+            }
+        }
+
+        errors
+    }
+}
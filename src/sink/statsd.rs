@@ -0,0 +1,140 @@
+use super::{Event, Finished, ResultSink};
+use anyhow::{anyhow, Context, Result};
+use futures::future::BoxFuture;
+use std::net::{ToSocketAddrs, UdpSocket};
+
+// Characters that separate the parts of a metric, in names and tag values.
+fn sanitize(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| match c {
+            ':' | '|' | '@' | '#' | ',' | '\n' | ' ' => '_',
+            c => c,
+        })
+        .collect()
+}
+
+/// Sends a timing and counters for every request, and the stats of every
+/// test at the end, over udp. With `dogstatsd` the endpoint, method and status
+/// are tags, otherwise the endpoint is part of the metric name.
+pub struct Statsd {
+    socket: UdpSocket,
+    prefix: String,
+    dogstatsd: bool,
+    desc: Option<String>,
+}
+
+impl Statsd {
+    pub fn new(addr: &str, prefix: String, dogstatsd: bool, desc: Option<String>) -> Result<Self> {
+        let addr = addr
+            .to_socket_addrs()
+            .with_context(|| format!("Failed to resolve statsd address {}", addr))?
+            .next()
+            .ok_or_else(|| anyhow!("Failed to resolve statsd address {}", addr))?;
+        let socket = match addr.is_ipv4() {
+            true => UdpSocket::bind("0.0.0.0:0"),
+            false => UdpSocket::bind("[::]:0"),
+        }
+        .context("Failed to bind a udp socket for statsd")?;
+        socket
+            .connect(addr)
+            .with_context(|| format!("Failed to connect to statsd at {}", addr))?;
+
+        Ok(Self {
+            socket,
+            prefix,
+            dogstatsd,
+            desc,
+        })
+    }
+
+    fn metric(&self, endpoint: &str, name: &str, value: String, tags: &[(&str, &str)]) -> String {
+        match self.dogstatsd {
+            true => {
+                let mut all_tags = vec![format!("endpoint:{}", sanitize(endpoint))];
+                if let Some(desc) = &self.desc {
+                    all_tags.push(format!("desc:{}", sanitize(desc)));
+                }
+                for (key, value) in tags {
+                    all_tags.push(format!("{}:{}", key, sanitize(value)));
+                }
+                format!("{}.{}:{}|#{}", self.prefix, name, value, all_tags.join(","))
+            }
+            false => format!("{}.{}.{}:{}", self.prefix, sanitize(endpoint), name, value),
+        }
+    }
+
+    fn send(&self, metrics: Vec<String>) -> Result<()> {
+        self.socket
+            .send(metrics.join("\n").as_bytes())
+            .context("Failed to send metrics to statsd")?;
+        Ok(())
+    }
+}
+
+impl ResultSink for Statsd {
+    fn name(&self) -> &'static str {
+        "statsd"
+    }
+
+    fn record(&self, event: &Event) -> Result<()> {
+        let name = &event.endpoint.name;
+        let method = event.endpoint.method.to_string();
+        let status = event.output.status.to_string();
+        let tags = [("method", method.as_str()), ("status", status.as_str())];
+
+        let mut metrics = vec![
+            self.metric(
+                name,
+                "request.duration",
+                format!("{}|ms", event.output.duration),
+                &tags,
+            ),
+            self.metric(name, "request.count", "1|c".to_string(), &tags),
+            self.metric(
+                name,
+                "response.bytes",
+                format!("{}|c", event.output.bytes),
+                &tags,
+            ),
+        ];
+        if let Some(error) = event.output.error_kind(event.endpoint.expected_status) {
+            let kind = error.to_string();
+            metrics.push(self.metric(
+                name,
+                "request.errors",
+                "1|c".to_string(),
+                &[("method", method.as_str()), ("kind", kind.as_str())],
+            ));
+        }
+
+        self.send(metrics)
+    }
+
+    fn finish<'a>(&'a self, run: &'a Finished<'a>) -> BoxFuture<'a, Result<()>> {
+        let metrics = run
+            .snapshot
+            .tests
+            .iter()
+            .flat_map(|test| {
+                let name = &test.config.endpoint_name;
+                vec![
+                    self.metric(
+                        name,
+                        "test.success",
+                        format!("{}|g", test.success as u8),
+                        &[],
+                    ),
+                    self.metric(
+                        name,
+                        "test.average_response_time",
+                        format!("{}|g", test.stats.average_response_time),
+                        &[],
+                    ),
+                ]
+            })
+            .collect::<Vec<_>>();
+
+        Box::pin(async move { self.send(metrics) })
+    }
+}
@@ -0,0 +1,47 @@
+use super::{post, Finished, ResultSink};
+use crate::report;
+use anyhow::Result;
+use futures::future::BoxFuture;
+use std::collections::HashMap;
+
+/// Posts the json report of the run once it finished.
+pub struct Webhook {
+    client: reqwest::Client,
+    url: String,
+    headers: HashMap<String, String>,
+}
+
+impl Webhook {
+    pub fn new(url: String, headers: HashMap<String, String>) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            url,
+            headers,
+        }
+    }
+}
+
+impl ResultSink for Webhook {
+    fn name(&self) -> &'static str {
+        "webhook"
+    }
+
+    fn finish<'a>(&'a self, run: &'a Finished<'a>) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            let body = report::json::render(
+                &run.snapshot.tests,
+                run.config,
+                run.latest,
+                run.snapshot.timestamp,
+            )?;
+            post(
+                &self.client,
+                &self.url,
+                &self.headers,
+                "application/json",
+                body,
+            )
+            .await
+        })
+    }
+}
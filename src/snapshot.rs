@@ -1,56 +1,172 @@
+use crate::config::BaselineStrategy;
+use crate::environment::{git, git_info, Environment};
 use crate::process::Test;
-use anyhow::{Context, Result};
+use crate::store::migrate::{self, SCHEMA_VERSION};
+use crate::store::{Query, SnapshotStore};
+use anyhow::{anyhow, Context, Result};
 use serde::{Deserialize, Serialize};
 use std::time::{SystemTime, UNIX_EPOCH};
 
-const SNAPSHOT_PATH: &str = "./.ballast_snapshot.json";
-
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Snapshot {
+    pub schema_version: u64,
+    pub id: String,
     pub tests: Vec<Test>,
     pub timestamp: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub desc: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub environment: Option<Environment>,
+    /// Kept regardless of the retention policy.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub pinned: bool,
 }
 
 impl Snapshot {
-    pub fn new(tests: Vec<Test>) -> Result<Self> {
+    pub fn new(tests: Vec<Test>, desc: Option<String>, environment: Environment) -> Result<Self> {
         let timestamp = SystemTime::now()
             .duration_since(UNIX_EPOCH)
             .context("Failed to get timestamp")?
             .as_secs();
 
-        Ok(Self { tests, timestamp })
+        Ok(Self {
+            schema_version: SCHEMA_VERSION,
+            id: Self::new_id(),
+            tests,
+            timestamp,
+            desc,
+            environment: Some(environment),
+            pinned: false,
+        })
     }
 
-    pub fn read() -> Result<Vec<Snapshot>> {
-        let snapshots: Vec<Snapshot> = match std::fs::read_to_string(SNAPSHOT_PATH) {
-            Ok(s) => serde_json::from_str(&s)
-                .context("Failed to parse .ballast_snapshot.json, where changes manually added?")?,
-            Err(_) => Vec::new(),
-        };
+    pub fn new_id() -> String {
+        uuid::Uuid::new_v4().simple().to_string()[..12].to_string()
+    }
 
-        return Ok(snapshots);
+    /// The snapshot in `store` with an id starting with `id`.
+    pub fn find(store: &dyn SnapshotStore, id: &str) -> Result<Self> {
+        let mut matching = store
+            .read()?
+            .into_iter()
+            .filter(|s| s.id.starts_with(id))
+            .collect::<Vec<_>>();
+        match matching.len() {
+            1 => Ok(matching.remove(0)),
+            0 => Err(anyhow!("No snapshot with id {}", id)),
+            _ => Err(anyhow!(
+                "More than one snapshot with an id starting with {}",
+                id
+            )),
+        }
     }
 
-    pub fn write(&self) -> Result<()> {
-        let mut snapshots = Self::read()?;
-        snapshots.push(self.clone());
-        let snapshot_json = serde_json::to_string(&snapshots)
-            .context("Failed to parse .ballast_snapshot.json, where changes manually added?")?;
-        std::fs::write(SNAPSHOT_PATH, snapshot_json)
-            .context("Failed to write to .ballast_snapshot.json")?;
+    /// The snapshots in a file outside the store, either a single exported
+    /// snapshot or a whole json store. Older ones are upgraded like the
+    /// snapshots of a store.
+    pub fn read_file(path: &str) -> Result<Vec<Self>> {
+        let contents =
+            std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path))?;
+        let values = match serde_json::from_str(&contents)
+            .with_context(|| format!("Failed to parse {}", path))?
+        {
+            serde_json::Value::Array(values) => values,
+            value => vec![value],
+        };
 
-        Ok(())
-    }
+        let mut snapshots = vec![];
+        for mut value in values {
+            migrate::upgrade(&mut value).with_context(|| format!("Failed to read {}", path))?;
+            let snapshot: Snapshot = serde_json::from_value(value)
+                .with_context(|| format!("{} isn't a ballast snapshot", path))?;
+            snapshots.push(snapshot);
+        }
+        snapshots.sort_by_key(|s| s.timestamp);
 
-    pub fn latest() -> Result<Option<Self>> {
-        let mut snapshots = Self::read()?;
+        Ok(snapshots)
+    }
 
-        if snapshots.len() == 0 {
-            return Ok(None);
+    /// The commit, branch and description of the snapshot, as far as known.
+    pub fn title(&self) -> String {
+        let mut title = vec![];
+        if let Some(git) = self.environment.as_ref().and_then(|e| e.git.as_ref()) {
+            title.push(format!(
+                "{} ({}{})",
+                &git.commit[..git.commit.len().min(7)],
+                git.branch,
+                match git.dirty {
+                    true => ", dirty",
+                    false => "",
+                }
+            ));
+        }
+        if let Some(desc) = &self.desc {
+            title.push(desc.clone());
         }
+        title.join(" ")
+    }
 
-        snapshots.sort_by_key(|s| s.timestamp);
+    fn commit(&self) -> Option<&str> {
+        self.environment
+            .as_ref()
+            .and_then(|e| e.git.as_ref())
+            .map(|g| g.commit.as_str())
+    }
+
+    /// The snapshot in `store` to compare a run against, picked by `strategy`.
+    pub fn latest(store: &dyn SnapshotStore, strategy: &BaselineStrategy) -> Result<Option<Self>> {
+        let baseline = match strategy {
+            BaselineStrategy::Latest => store.read()?.pop(),
+            BaselineStrategy::Branch => {
+                let branch = git_info()
+                    .ok_or_else(|| {
+                        anyhow!("Picking the baseline by branch needs a git repository")
+                    })?
+                    .branch;
+                store
+                    .query(&Query {
+                        branch: Some(branch),
+                        ..Default::default()
+                    })?
+                    .pop()
+            }
+            BaselineStrategy::MergeBase { branch } => {
+                let merge_base = git(&["merge-base", "HEAD", branch]).ok_or_else(|| {
+                    anyhow!("Failed to find the merge base of HEAD and {}", branch)
+                })?;
+                let snapshot = store
+                    .query(&Query {
+                        commit: Some(merge_base.clone()),
+                        ..Default::default()
+                    })?
+                    .pop();
+                // Without a snapshot of the merge base itself the closest one
+                // before it is used, e.g. when main moved on since the last run.
+                match snapshot {
+                    Some(snapshot) => Some(snapshot),
+                    None => store.read()?.into_iter().rev().find(|s| {
+                        s.commit().is_some_and(|commit| {
+                            git(&["merge-base", "--is-ancestor", commit, &merge_base]).is_some()
+                        })
+                    }),
+                }
+            }
+            BaselineStrategy::Ref { reference } => {
+                let commit = git(&[
+                    "rev-parse",
+                    "--verify",
+                    &format!("{}^{{commit}}", reference),
+                ])
+                .ok_or_else(|| anyhow!("Unknown git ref {}", reference))?;
+                store
+                    .query(&Query {
+                        commit: Some(commit),
+                        ..Default::default()
+                    })?
+                    .pop()
+            }
+        };
 
-        Ok(Some(snapshots.last().unwrap().clone()))
+        Ok(baseline)
     }
 }
@@ -0,0 +1,137 @@
+use super::{migrate, write_atomic, SnapshotStore};
+use crate::snapshot::Snapshot;
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+pub const DEFAULT_PATH: &str = "./.ballast_snapshots";
+
+/// A json file per snapshot, so runs on different branches add files instead
+/// of conflicting changes to a single one.
+pub struct DirectoryStore {
+    path: String,
+    warnings: Mutex<Vec<String>>,
+}
+
+impl DirectoryStore {
+    pub fn new(path: &str) -> Self {
+        Self {
+            path: path.to_string(),
+            warnings: Mutex::new(vec![]),
+        }
+    }
+
+    fn files(&self) -> Result<Vec<PathBuf>> {
+        let entries = match std::fs::read_dir(&self.path) {
+            Ok(entries) => entries,
+            Err(_) => return Ok(vec![]),
+        };
+
+        let mut files = vec![];
+        for entry in entries {
+            let path = entry?.path();
+            if path.extension().and_then(|e| e.to_str()) == Some("json") {
+                files.push(path);
+            }
+        }
+
+        Ok(files)
+    }
+
+    /// Every snapshot with the file it was read from, oldest first. Files
+    /// that can't be parsed are skipped with a warning and left as they are,
+    /// a broken run shouldn't stop the others from being read.
+    fn read_files(&self) -> Result<Vec<(PathBuf, Snapshot)>> {
+        let mut snapshots: Vec<(PathBuf, Snapshot)> = vec![];
+        for path in self.files()? {
+            let contents = std::fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+            let mut value: serde_json::Value = match serde_json::from_str(&contents) {
+                Ok(value) => value,
+                Err(e) => {
+                    self.skip(&path, e);
+                    continue;
+                }
+            };
+            migrate::upgrade(&mut value)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+            match serde_json::from_value(value) {
+                Ok(snapshot) => snapshots.push((path, snapshot)),
+                Err(e) => self.skip(&path, e),
+            }
+        }
+        snapshots.sort_by_key(|(_, s)| s.timestamp);
+
+        Ok(snapshots)
+    }
+
+    fn skip(&self, path: &Path, error: serde_json::Error) {
+        self.warnings.lock().unwrap().push(format!(
+            "Skipped {} since it couldn't be parsed, {}",
+            path.display(),
+            error
+        ));
+    }
+
+    /// The timestamp keeps the files in order, the id apart when two runs
+    /// finish within the same second.
+    fn file(&self, snapshot: &Snapshot) -> PathBuf {
+        Path::new(&self.path).join(format!("{}-{}.json", snapshot.timestamp, snapshot.id))
+    }
+}
+
+impl SnapshotStore for DirectoryStore {
+    fn location(&self) -> &str {
+        &self.path
+    }
+
+    fn read(&self) -> Result<Vec<Snapshot>> {
+        Ok(self
+            .read_files()?
+            .into_iter()
+            .map(|(_, snapshot)| snapshot)
+            .collect())
+    }
+
+    fn write(&self, snapshot: &Snapshot) -> Result<()> {
+        std::fs::create_dir_all(&self.path)
+            .with_context(|| format!("Failed to create {}", self.path))?;
+        let snapshot_json =
+            serde_json::to_string_pretty(snapshot).context("Failed to serialize the snapshot")?;
+        write_atomic(self.file(snapshot), snapshot_json.as_bytes())?;
+
+        Ok(())
+    }
+
+    fn update(&self, change: &mut dyn FnMut(Vec<Snapshot>) -> Vec<Snapshot>) -> Result<()> {
+        // Only the files read here can be removed, the ones other runs write
+        // in the meantime are left alone.
+        let (files, snapshots): (Vec<_>, Vec<_>) = self.read_files()?.into_iter().unzip();
+        let snapshots = change(snapshots);
+
+        for snapshot in &snapshots {
+            // Unchanged files aren't touched, to keep the diff of a commit
+            // of the directory to the snapshots that changed.
+            let snapshot_json = serde_json::to_string_pretty(snapshot)
+                .context("Failed to serialize the snapshot")?;
+            let path = self.file(snapshot);
+            if std::fs::read_to_string(&path).ok().as_ref() != Some(&snapshot_json) {
+                std::fs::create_dir_all(&self.path)
+                    .with_context(|| format!("Failed to create {}", self.path))?;
+                write_atomic(&path, snapshot_json.as_bytes())?;
+            }
+        }
+        for path in files {
+            if snapshots.iter().all(|s| self.file(s) != path) {
+                std::fs::remove_file(&path)
+                    .with_context(|| format!("Failed to remove {}", path.display()))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn take_warnings(&self) -> Vec<String> {
+        std::mem::take(&mut *self.warnings.lock().unwrap())
+    }
+}
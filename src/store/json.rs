@@ -0,0 +1,201 @@
+use super::{migrate, write_atomic, SnapshotStore};
+use crate::snapshot::Snapshot;
+use anyhow::{Context, Result};
+use serde_json::Value;
+use std::fs::File;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub const DEFAULT_PATH: &str = "./.ballast_snapshot.json";
+
+/// Every snapshot in one json array, rewritten on every write.
+pub struct JsonStore {
+    path: String,
+    warnings: Mutex<Vec<String>>,
+}
+
+/// The snapshots of the file, and whether some of it couldn't be parsed.
+struct Contents {
+    snapshots: Vec<Snapshot>,
+    damaged: bool,
+}
+
+/// The top level objects of a json array, even when the array is cut off,
+/// e.g. by a crash in the middle of a write.
+fn split_entries(contents: &str) -> Vec<&str> {
+    let start = match contents.find('[') {
+        Some(start) => start + 1,
+        None => return vec![],
+    };
+
+    let mut entries = vec![];
+    let mut depth = 0;
+    let mut entry_start = 0;
+    let mut in_string = false;
+    let mut escaped = false;
+    for (i, c) in contents[start..].char_indices() {
+        let i = start + i;
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' => in_string = true,
+            '{' | '[' => {
+                if depth == 0 {
+                    entry_start = i;
+                }
+                depth += 1;
+            }
+            '}' | ']' if depth == 0 => break,
+            '}' | ']' => {
+                depth -= 1;
+                if depth == 0 {
+                    entries.push(&contents[entry_start..=i]);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    entries
+}
+
+impl JsonStore {
+    pub fn new(path: &str) -> Self {
+        Self {
+            path: path.to_string(),
+            warnings: Mutex::new(vec![]),
+        }
+    }
+
+    fn warn(&self, warning: String) {
+        // The file is read again before a write, it's still the same damage.
+        let mut warnings = self.warnings.lock().unwrap();
+        if !warnings.contains(&warning) {
+            warnings.push(warning);
+        }
+    }
+
+    /// Held while the file is updated, released when dropped.
+    fn lock(&self) -> Result<File> {
+        let lock_path = format!("{}.lock", self.path);
+        let lock =
+            File::create(&lock_path).with_context(|| format!("Failed to create {}", lock_path))?;
+        lock.lock()
+            .with_context(|| format!("Failed to lock {}", lock_path))?;
+        Ok(lock)
+    }
+
+    /// Copies the file before it's rewritten without the snapshots that
+    /// couldn't be parsed.
+    fn back_up(&self) -> Result<()> {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let backup = format!("{}.{}.bak", self.path, timestamp);
+        std::fs::copy(&self.path, &backup)
+            .with_context(|| format!("Failed to back up {} to {}", self.path, backup))?;
+        self.warn(format!(
+            "Kept a copy of the damaged {} at {}",
+            self.path, backup
+        ));
+        Ok(())
+    }
+
+    /// Reads what can be read of the file, skipping the snapshots that can't
+    /// be parsed instead of failing every run after one bad write.
+    fn read_contents(&self) -> Result<Contents> {
+        let contents = match std::fs::read_to_string(&self.path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(Contents {
+                    snapshots: vec![],
+                    damaged: false,
+                })
+            }
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {}", self.path)),
+        };
+
+        let (entries, mut damaged) = match serde_json::from_str::<Vec<Value>>(&contents) {
+            Ok(values) => (values, false),
+            Err(_) => {
+                let values = split_entries(&contents)
+                    .into_iter()
+                    .filter_map(|entry| serde_json::from_str(entry).ok())
+                    .collect::<Vec<Value>>();
+                self.warn(format!(
+                    "{} is damaged, recovered {} snapshots from it",
+                    self.path,
+                    values.len()
+                ));
+                (values, true)
+            }
+        };
+
+        let total = entries.len();
+        let mut snapshots = vec![];
+        for mut entry in entries {
+            migrate::upgrade(&mut entry)
+                .with_context(|| format!("Failed to read {}", self.path))?;
+            if let Ok(snapshot) = serde_json::from_value::<Snapshot>(entry) {
+                snapshots.push(snapshot);
+            }
+        }
+        if snapshots.len() < total {
+            self.warn(format!(
+                "Skipped {} snapshots of {} that couldn't be parsed",
+                total - snapshots.len(),
+                self.path
+            ));
+            damaged = true;
+        }
+        snapshots.sort_by_key(|s| s.timestamp);
+
+        Ok(Contents { snapshots, damaged })
+    }
+}
+
+impl SnapshotStore for JsonStore {
+    fn location(&self) -> &str {
+        &self.path
+    }
+
+    fn read(&self) -> Result<Vec<Snapshot>> {
+        Ok(self.read_contents()?.snapshots)
+    }
+
+    fn write(&self, snapshot: &Snapshot) -> Result<()> {
+        self.update(&mut |mut snapshots| {
+            snapshots.push(snapshot.clone());
+            snapshots
+        })
+    }
+
+    fn update(&self, change: &mut dyn FnMut(Vec<Snapshot>) -> Vec<Snapshot>) -> Result<()> {
+        // Another run could write between the read and the write below and
+        // its snapshot would be lost, so the whole update holds a lock.
+        let _lock = self.lock()?;
+
+        let Contents { snapshots, damaged } = self.read_contents()?;
+        if damaged {
+            self.back_up()?;
+        }
+
+        let snapshot_json = serde_json::to_string(&change(snapshots))
+            .context("Failed to serialize the snapshots")?;
+        write_atomic(&self.path, snapshot_json.as_bytes())?;
+
+        Ok(())
+    }
+
+    fn take_warnings(&self) -> Vec<String> {
+        std::mem::take(&mut *self.warnings.lock().unwrap())
+    }
+}
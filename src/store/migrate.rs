@@ -0,0 +1,52 @@
+use crate::environment::hash;
+use anyhow::{anyhow, Result};
+use serde_json::{Map, Value};
+
+/// The version of the snapshot layout written by this version of ballast.
+/// Increase it and add a migration whenever a field of a snapshot is added
+/// without a default, renamed, removed or changes meaning.
+pub const SCHEMA_VERSION: u64 = 1;
+
+type Migration = fn(&mut Map<String, Value>);
+
+/// `MIGRATIONS[n]` upgrades a snapshot of version n to version n + 1.
+const MIGRATIONS: &[Migration] = &[v0_to_v1];
+
+/// Version 0 is every snapshot written before the version was recorded,
+/// version 1 gives every snapshot an id. The id of an old snapshot is a hash
+/// of its contents so it's the same every time it's read until it's migrated.
+fn v0_to_v1(snapshot: &mut Map<String, Value>) {
+    let id = hash(Value::Object(snapshot.clone()).to_string().as_bytes());
+    snapshot.insert("id".to_string(), Value::String(id[..12].to_string()));
+}
+
+pub fn version(snapshot: &Value) -> u64 {
+    snapshot
+        .get("schema_version")
+        .and_then(Value::as_u64)
+        .unwrap_or(0)
+}
+
+/// Upgrades a snapshot read from a store to `SCHEMA_VERSION` in place. Fails
+/// for a snapshot written by a newer version of ballast, rather than reading
+/// it wrong or dropping it on the next write.
+pub fn upgrade(snapshot: &mut Value) -> Result<()> {
+    let from = version(snapshot);
+    if from > SCHEMA_VERSION {
+        return Err(anyhow!(
+            "Found a snapshot with schema version {} but this version of ballast only reads up to {}, upgrade ballast to read it",
+            from,
+            SCHEMA_VERSION
+        ));
+    }
+
+    // Anything but an object fails to parse as a snapshot afterwards.
+    if let Some(snapshot) = snapshot.as_object_mut() {
+        for migration in &MIGRATIONS[from as usize..] {
+            migration(snapshot);
+        }
+        snapshot.insert("schema_version".to_string(), SCHEMA_VERSION.into());
+    }
+
+    Ok(())
+}
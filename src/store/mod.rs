@@ -0,0 +1,116 @@
+pub mod directory;
+pub mod json;
+pub mod migrate;
+pub mod sqlite;
+
+use crate::config::{Config, StorageConfig};
+use crate::snapshot::Snapshot;
+use anyhow::{Context, Result};
+use std::io::Write;
+use std::path::Path;
+
+/// Which snapshots to read, every unset field matches any snapshot.
+#[derive(Debug, Default, Clone)]
+pub struct Query {
+    /// Snapshots with a test of this name.
+    pub endpoint: Option<String>,
+    pub branch: Option<String>,
+    pub commit: Option<String>,
+    /// Unix timestamp in seconds, inclusive.
+    pub since: Option<u64>,
+    /// Unix timestamp in seconds, inclusive.
+    pub until: Option<u64>,
+}
+
+impl Query {
+    pub fn matches(&self, snapshot: &Snapshot) -> bool {
+        let git = snapshot.environment.as_ref().and_then(|e| e.git.as_ref());
+        self.endpoint.as_ref().is_none_or(|endpoint| {
+            snapshot
+                .tests
+                .iter()
+                .any(|t| &t.config.endpoint_name == endpoint)
+        }) && self
+            .branch
+            .as_ref()
+            .is_none_or(|branch| git.is_some_and(|g| &g.branch == branch))
+            && self
+                .commit
+                .as_ref()
+                .is_none_or(|commit| git.is_some_and(|g| &g.commit == commit))
+            && self.since.is_none_or(|since| snapshot.timestamp >= since)
+            && self.until.is_none_or(|until| snapshot.timestamp <= until)
+    }
+}
+
+/// Where the snapshots of every run are saved and read back from.
+pub trait SnapshotStore {
+    /// Where the snapshots are, shown in messages.
+    fn location(&self) -> &str;
+
+    /// Every saved snapshot, oldest first.
+    fn read(&self) -> Result<Vec<Snapshot>>;
+
+    fn write(&self, snapshot: &Snapshot) -> Result<()>;
+
+    /// Replaces the snapshots in the store with what `change` makes of them,
+    /// without losing snapshots other runs write in the meantime.
+    fn update(&self, change: &mut dyn FnMut(Vec<Snapshot>) -> Vec<Snapshot>) -> Result<()>;
+
+    /// The snapshots matching `query`, oldest first.
+    fn query(&self, query: &Query) -> Result<Vec<Snapshot>> {
+        Ok(self
+            .read()?
+            .into_iter()
+            .filter(|s| query.matches(s))
+            .collect())
+    }
+
+    /// What went wrong since the last call without failing, e.g. snapshots
+    /// that were skipped since they couldn't be parsed.
+    fn take_warnings(&self) -> Vec<String> {
+        vec![]
+    }
+}
+
+/// Writes `contents` to a temporary file next to `path` and renames it over
+/// `path`, so a crash leaves either the old or the new file and never half of
+/// one.
+pub fn write_atomic(path: impl AsRef<Path>, contents: &[u8]) -> Result<()> {
+    let path = path.as_ref();
+    let file_name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let temp = path.with_file_name(format!(".{}.{}.tmp", file_name, std::process::id()));
+
+    let write = || -> std::io::Result<()> {
+        let mut file = std::fs::File::create(&temp)?;
+        file.write_all(contents)?;
+        file.sync_all()?;
+        std::fs::rename(&temp, path)
+    };
+    if let Err(e) = write() {
+        let _ = std::fs::remove_file(&temp);
+        return Err(e).with_context(|| format!("Failed to write to {}", path.display()));
+    }
+
+    Ok(())
+}
+
+/// The store picked by the `storage` of the config, the json file by default.
+pub fn open(config: &Config) -> Result<Box<dyn SnapshotStore>> {
+    let store: Box<dyn SnapshotStore> = match &config.storage {
+        None => Box::new(json::JsonStore::new(json::DEFAULT_PATH)),
+        Some(StorageConfig::Json { path }) => Box::new(json::JsonStore::new(
+            path.as_deref().unwrap_or(json::DEFAULT_PATH),
+        )),
+        Some(StorageConfig::Directory { path }) => Box::new(directory::DirectoryStore::new(
+            path.as_deref().unwrap_or(directory::DEFAULT_PATH),
+        )),
+        Some(StorageConfig::Sqlite { path }) => Box::new(sqlite::SqliteStore::open(
+            path.as_deref().unwrap_or(sqlite::DEFAULT_PATH),
+        )?),
+    };
+    Ok(store)
+}
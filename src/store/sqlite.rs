@@ -0,0 +1,190 @@
+use super::{migrate, Query, SnapshotStore};
+use crate::snapshot::Snapshot;
+use anyhow::{Context, Result};
+use rusqlite::types::Value;
+use rusqlite::Connection;
+use std::sync::Mutex;
+use std::time::Duration;
+
+pub const DEFAULT_PATH: &str = "./.ballast_snapshots.db";
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS snapshots (
+    id INTEGER PRIMARY KEY,
+    timestamp INTEGER NOT NULL,
+    branch TEXT,
+    commit_hash TEXT,
+    data TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS snapshots_timestamp ON snapshots (timestamp);
+CREATE INDEX IF NOT EXISTS snapshots_branch ON snapshots (branch, timestamp);
+CREATE INDEX IF NOT EXISTS snapshots_commit ON snapshots (commit_hash, timestamp);
+CREATE TABLE IF NOT EXISTS snapshot_endpoints (
+    snapshot_id INTEGER NOT NULL REFERENCES snapshots (id) ON DELETE CASCADE,
+    endpoint TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS snapshot_endpoints_endpoint ON snapshot_endpoints (endpoint, snapshot_id);
+";
+
+/// Snapshots in a sqlite database. The whole snapshot is kept as json, with
+/// the fields queries filter on in indexed columns next to it.
+pub struct SqliteStore {
+    path: String,
+    connection: Connection,
+    warnings: Mutex<Vec<String>>,
+}
+
+impl SqliteStore {
+    pub fn open(path: &str) -> Result<Self> {
+        let connection =
+            Connection::open(path).with_context(|| format!("Failed to open {}", path))?;
+        // Runs at the same time wait for each other's writes instead of failing.
+        connection.busy_timeout(Duration::from_secs(30))?;
+        connection
+            .execute_batch(SCHEMA)
+            .with_context(|| format!("Failed to create the tables in {}", path))?;
+        Ok(Self {
+            path: path.to_string(),
+            connection,
+            warnings: Mutex::new(vec![]),
+        })
+    }
+
+    fn select(&self, conditions: &[&str], params: Vec<Value>) -> Result<Vec<Snapshot>> {
+        let mut sql = "SELECT id, data FROM snapshots".to_string();
+        if !conditions.is_empty() {
+            sql.push_str(" WHERE ");
+            sql.push_str(&conditions.join(" AND "));
+        }
+        sql.push_str(" ORDER BY timestamp, id");
+
+        let mut statement = self.connection.prepare(&sql)?;
+        let rows = statement.query_map(rusqlite::params_from_iter(params), |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+        })?;
+        let mut snapshots = vec![];
+        for row in rows {
+            let (id, data) = row?;
+            let parsed = match serde_json::from_str::<serde_json::Value>(&data) {
+                Ok(mut value) => {
+                    migrate::upgrade(&mut value)
+                        .with_context(|| format!("Failed to read {}", self.path))?;
+                    serde_json::from_value::<Snapshot>(value)
+                }
+                Err(e) => Err(e),
+            };
+            match parsed {
+                Ok(snapshot) => snapshots.push(snapshot),
+                Err(e) => self.warnings.lock().unwrap().push(format!(
+                    "Skipped snapshot {} of {} since it couldn't be parsed, {}",
+                    id, self.path, e
+                )),
+            }
+        }
+
+        Ok(snapshots)
+    }
+}
+
+fn insert(connection: &Connection, snapshot: &Snapshot) -> Result<()> {
+    let git = snapshot.environment.as_ref().and_then(|e| e.git.as_ref());
+    let data = serde_json::to_string(snapshot).context("Failed to serialize the snapshot")?;
+
+    connection.execute(
+        "INSERT INTO snapshots (timestamp, branch, commit_hash, data) VALUES (?1, ?2, ?3, ?4)",
+        rusqlite::params![
+            snapshot.timestamp as i64,
+            git.map(|g| g.branch.as_str()),
+            git.map(|g| g.commit.as_str()),
+            data
+        ],
+    )?;
+    let id = connection.last_insert_rowid();
+    for test in &snapshot.tests {
+        connection.execute(
+            "INSERT INTO snapshot_endpoints (snapshot_id, endpoint) VALUES (?1, ?2)",
+            rusqlite::params![id, test.config.endpoint_name],
+        )?;
+    }
+
+    Ok(())
+}
+
+impl SnapshotStore for SqliteStore {
+    fn location(&self) -> &str {
+        &self.path
+    }
+
+    fn read(&self) -> Result<Vec<Snapshot>> {
+        self.select(&[], vec![])
+    }
+
+    fn write(&self, snapshot: &Snapshot) -> Result<()> {
+        let transaction = self.connection.unchecked_transaction()?;
+        insert(&transaction, snapshot)?;
+        transaction
+            .commit()
+            .with_context(|| format!("Failed to write to {}", self.path))?;
+
+        Ok(())
+    }
+
+    fn update(&self, change: &mut dyn FnMut(Vec<Snapshot>) -> Vec<Snapshot>) -> Result<()> {
+        // Immediate so other runs wait for the update instead of writing
+        // between the read and the write.
+        self.connection.execute_batch("BEGIN IMMEDIATE")?;
+        let updated = self.read().and_then(|snapshots| {
+            let snapshots = change(snapshots);
+            self.connection
+                .execute_batch("DELETE FROM snapshot_endpoints; DELETE FROM snapshots;")?;
+            for snapshot in &snapshots {
+                insert(&self.connection, snapshot)?;
+            }
+            Ok(())
+        });
+        match updated {
+            Ok(()) => self
+                .connection
+                .execute_batch("COMMIT")
+                .with_context(|| format!("Failed to write to {}", self.path))?,
+            Err(e) => {
+                let _ = self.connection.execute_batch("ROLLBACK");
+                return Err(e);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn query(&self, query: &Query) -> Result<Vec<Snapshot>> {
+        let mut conditions = vec![];
+        let mut params = vec![];
+        if let Some(endpoint) = &query.endpoint {
+            conditions
+                .push("id IN (SELECT snapshot_id FROM snapshot_endpoints WHERE endpoint = ?)");
+            params.push(Value::Text(endpoint.clone()));
+        }
+        if let Some(branch) = &query.branch {
+            conditions.push("branch = ?");
+            params.push(Value::Text(branch.clone()));
+        }
+        if let Some(commit) = &query.commit {
+            conditions.push("commit_hash = ?");
+            params.push(Value::Text(commit.clone()));
+        }
+        if let Some(since) = query.since {
+            conditions.push("timestamp >= ?");
+            params.push(Value::Integer(since as i64));
+        }
+        if let Some(until) = query.until {
+            conditions.push("timestamp <= ?");
+            params.push(Value::Integer(until as i64));
+        }
+
+        self.select(&conditions, params)
+    }
+
+    fn take_warnings(&self) -> Vec<String> {
+        std::mem::take(&mut *self.warnings.lock().unwrap())
+    }
+}
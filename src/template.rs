@@ -0,0 +1,172 @@
+use crate::feeder::Row;
+use anyhow::{anyhow, Result};
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+// Variables may reference other variables, this bounds how deep that can go
+// so that a self referencing variable errors instead of overflowing the stack.
+const MAX_DEPTH: usize = 8;
+
+/// Renders `${...}` variables and `{{...}}` generators in request templates.
+///
+/// `${env:NAME}` reads an environment variable and `${name}` a variable from the
+/// `variables` section of ballast.json, `${data:column}` reads a column of the
+/// row fed to the request by the endpoint's data file. Generators are evaluated
+/// every time a template is rendered, so each request gets its own values.
+pub struct Templater {
+    variables: HashMap<String, String>,
+    seq: AtomicU64,
+}
+
+impl Templater {
+    pub fn new(variables: HashMap<String, String>) -> Self {
+        Self {
+            variables,
+            seq: AtomicU64::new(0),
+        }
+    }
+
+    pub fn render(&self, input: &str, row: Option<&Row>) -> Result<String> {
+        self.render_depth(input, row, 0)
+    }
+
+    /// Renders every string inside of a json value, keys are left untouched.
+    pub fn render_value(&self, value: &Value, row: Option<&Row>) -> Result<Value> {
+        Ok(match value {
+            Value::String(s) => Value::String(self.render(s, row)?),
+            Value::Array(values) => Value::Array(
+                values
+                    .iter()
+                    .map(|v| self.render_value(v, row))
+                    .collect::<Result<Vec<_>>>()?,
+            ),
+            Value::Object(map) => Value::Object(
+                map.iter()
+                    .map(|(k, v)| Ok((k.clone(), self.render_value(v, row)?)))
+                    .collect::<Result<_>>()?,
+            ),
+            other => other.clone(),
+        })
+    }
+
+    fn render_depth(&self, input: &str, row: Option<&Row>, depth: usize) -> Result<String> {
+        if depth > MAX_DEPTH {
+            return Err(anyhow!(
+                "Template variables are nested too deeply in {}",
+                input
+            ));
+        }
+
+        let mut output = String::with_capacity(input.len());
+        let mut rest = input;
+
+        loop {
+            let next = [rest.find("${"), rest.find("{{")]
+                .into_iter()
+                .flatten()
+                .min();
+
+            let start = match next {
+                Some(start) => start,
+                None => {
+                    output.push_str(rest);
+                    break;
+                }
+            };
+
+            output.push_str(&rest[..start]);
+            let is_variable = rest[start..].starts_with("${");
+            let close = match is_variable {
+                true => "}",
+                false => "}}",
+            };
+
+            let inner_start = start + 2;
+            let end = rest[inner_start..]
+                .find(close)
+                .ok_or_else(|| anyhow!("Unterminated template expression in {}", input))?;
+            let inner = rest[inner_start..inner_start + end].trim();
+
+            match is_variable {
+                true => output.push_str(&self.variable(inner, row, depth)?),
+                false => output.push_str(&self.generate(inner)?),
+            }
+
+            rest = &rest[inner_start + end + close.len()..];
+        }
+
+        Ok(output)
+    }
+
+    fn variable(&self, name: &str, row: Option<&Row>, depth: usize) -> Result<String> {
+        if let Some(env) = name.strip_prefix("env:") {
+            return std::env::var(env)
+                .map_err(|_| anyhow!("Environment variable {} is not set", env));
+        }
+
+        if let Some(column) = name.strip_prefix("data:") {
+            return match row.and_then(|row| row.get(column)) {
+                Some(value) => Ok(value.clone()),
+                None => Err(anyhow!("Data column {} is not available", column)),
+            };
+        }
+
+        match self.variables.get(name) {
+            Some(value) => self.render_depth(value, row, depth + 1),
+            None => Err(anyhow!("Unknown template variable {}", name)),
+        }
+    }
+
+    fn generate(&self, expression: &str) -> Result<String> {
+        let mut parts = expression.split_whitespace();
+        let name = parts.next().unwrap_or_default();
+        let args = parts.collect::<Vec<_>>();
+        let mut rng = rand::thread_rng();
+
+        let arg = |i: usize| -> Result<i64> {
+            args.get(i)
+                .ok_or_else(|| anyhow!("Missing argument {} for {{{{{}}}}}", i + 1, name))?
+                .parse::<i64>()
+                .map_err(|_| anyhow!("Invalid argument {} for {{{{{}}}}}", i + 1, name))
+        };
+
+        let value = match name {
+            "uuid" => uuid::Uuid::new_v4().to_string(),
+            "seq" => self.seq.fetch_add(1, Ordering::Relaxed).to_string(),
+            "now_iso" => chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+            "now_unix" => chrono::Utc::now().timestamp().to_string(),
+            "random_int" => {
+                let (min, max) = (arg(0)?, arg(1)?);
+                if min > max {
+                    return Err(anyhow!(
+                        "Invalid range {} {} for {{{{random_int}}}}",
+                        min,
+                        max
+                    ));
+                }
+                rng.gen_range(min..=max).to_string()
+            }
+            "random_string" => {
+                let len = match args.is_empty() {
+                    true => 16,
+                    false => arg(0)? as usize,
+                };
+                random_string(&mut rng, len)
+            }
+            "random_email" => format!("{}@example.com", random_string(&mut rng, 12).to_lowercase()),
+            _ => return Err(anyhow!("Unknown template generator {{{{{}}}}}", expression)),
+        };
+
+        Ok(value)
+    }
+}
+
+fn random_string(rng: &mut impl Rng, len: usize) -> String {
+    rng.sample_iter(&Alphanumeric)
+        .take(len)
+        .map(char::from)
+        .collect()
+}
@@ -0,0 +1,292 @@
+use crate::compare::metrics;
+use crate::dashboard::sparkline;
+use crate::printer::Printer;
+use crate::report::format_timestamp;
+use crate::snapshot::Snapshot;
+use crate::store::{Query, SnapshotStore};
+use anyhow::Result;
+
+// Segments shorter than this are too short to tell a change from noise.
+const MIN_SEGMENT: usize = 3;
+// How much better two lines have to fit than one, as an F statistic.
+const MIN_SCORE: f64 = 16.0;
+// Changes and drifts smaller than this, relative to where they started,
+// aren't reported.
+const MIN_CHANGE: f64 = 0.1;
+// How many standard errors the slope of a drift has to be away from flat.
+const MIN_DRIFT_SCORE: f64 = 4.0;
+
+fn mean(values: &[f64]) -> f64 {
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+/// A least squares line through the values, by their index.
+struct Line {
+    intercept: f64,
+    slope: f64,
+    squared_error: f64,
+    /// How many standard errors the slope is away from 0.
+    slope_score: f64,
+}
+
+fn fit_line(values: &[f64]) -> Line {
+    let n = values.len() as f64;
+    let x_mean = (n - 1.0) / 2.0;
+    let y_mean = mean(values);
+    let (mut xy, mut xx) = (0.0, 0.0);
+    for (x, y) in values.iter().enumerate() {
+        xy += (x as f64 - x_mean) * (y - y_mean);
+        xx += (x as f64 - x_mean).powi(2);
+    }
+    let slope = match xx > 0.0 {
+        true => xy / xx,
+        false => 0.0,
+    };
+    let intercept = y_mean - slope * x_mean;
+    let residual = values
+        .iter()
+        .enumerate()
+        .map(|(x, y)| (y - (intercept + slope * x as f64)).powi(2))
+        .sum::<f64>();
+    let slope_error = match n > 2.0 && xx > 0.0 {
+        true => (residual / (n - 2.0) / xx).sqrt(),
+        false => 0.0,
+    };
+
+    Line {
+        intercept,
+        slope,
+        squared_error: residual,
+        slope_score: match slope_error > 0.0 {
+            true => slope.abs() / slope_error,
+            false => f64::INFINITY,
+        },
+    }
+}
+
+/// A step in the values at `index`, from `before` (where the values before
+/// it were heading) to `after`.
+struct Step {
+    index: usize,
+    before: f64,
+    after: f64,
+    score: f64,
+}
+
+/// Where a line through the values before and one through the values after
+/// fit the values best, and how much better they fit than a single line. A
+/// steady drift fits a single line about as well, a step doesn't.
+fn best_step(values: &[f64]) -> Option<Step> {
+    let n = values.len();
+    if n < 2 * MIN_SEGMENT {
+        return None;
+    }
+
+    // Perfectly flat segments would make any step infinitely significant,
+    // the noise is assumed to be at least 1% of the values.
+    let noise_floor = (mean(values).abs() * 0.01).powi(2);
+    let single = fit_line(values).squared_error;
+    let mut best: Option<Step> = None;
+    for k in MIN_SEGMENT..=n - MIN_SEGMENT {
+        let (before, after) = (fit_line(&values[..k]), fit_line(&values[k..]));
+        let error = before.squared_error + after.squared_error;
+        let variance = (error / (n - 4).max(1) as f64).max(noise_floor);
+        if variance == 0.0 {
+            continue;
+        }
+        let score = (single - error) / 2.0 / variance;
+        if best.as_ref().is_none_or(|best| score > best.score) {
+            best = Some(Step {
+                index: k,
+                before: before.intercept + before.slope * k as f64,
+                after: after.intercept,
+                score,
+            });
+        }
+    }
+
+    best
+}
+
+/// The steps where the values moved to a different level, found by splitting
+/// the values at the most significant step and repeating that on both sides.
+fn steps(values: &[f64]) -> Vec<Step> {
+    fn split(values: &[f64], offset: usize, steps: &mut Vec<Step>) {
+        let step = match best_step(values) {
+            Some(step) => step,
+            None => return,
+        };
+        let change = match step.before.abs() > 0.0 {
+            true => (step.after - step.before).abs() / step.before.abs(),
+            false => f64::INFINITY,
+        };
+        if step.score < MIN_SCORE || change < MIN_CHANGE {
+            return;
+        }
+
+        let index = step.index;
+        split(&values[..index], offset, steps);
+        steps.push(Step {
+            index: offset + index,
+            ..step
+        });
+        split(&values[index..], offset + index, steps);
+    }
+
+    let mut steps = vec![];
+    split(values, 0, &mut steps);
+    steps
+}
+
+/// A steady change of the values, per snapshot and over all of them relative
+/// to the start.
+pub struct Drift {
+    pub per_snapshot: f64,
+    pub relative: f64,
+}
+
+pub fn drift(values: &[f64]) -> Option<Drift> {
+    if values.len() < 2 * MIN_SEGMENT {
+        return None;
+    }
+    let line = fit_line(values);
+    let total = line.slope * (values.len() - 1) as f64;
+    let relative = match line.intercept.abs() > 0.0 {
+        true => total / line.intercept.abs(),
+        false => return None,
+    };
+    match line.slope_score >= MIN_DRIFT_SCORE && relative.abs() >= MIN_CHANGE {
+        true => Some(Drift {
+            per_snapshot: line.slope,
+            relative,
+        }),
+        false => None,
+    }
+}
+
+fn percent(from: f64, to: f64) -> String {
+    match from != 0.0 {
+        true => format!("{:+.1}%", (to - from) / from * 100.0),
+        false => "n/a".to_string(),
+    }
+}
+
+/// Prints every metric of the tests over the last `last` snapshots, with the
+/// points where they changed and whether they have been drifting since.
+pub fn trend(
+    store: &dyn SnapshotStore,
+    endpoint: Option<String>,
+    last: usize,
+    printer: &Printer,
+) -> Result<()> {
+    let mut snapshots = store.query(&Query {
+        endpoint: endpoint.clone(),
+        ..Default::default()
+    })?;
+    snapshots.drain(..snapshots.len().saturating_sub(last));
+    if snapshots.is_empty() {
+        let message = match &endpoint {
+            Some(endpoint) => format!("No snapshots found with a test named {}", endpoint),
+            None => "No snapshots found".to_string(),
+        };
+        printer.print_with_red("ERROR", &message, 0);
+        return Ok(());
+    }
+
+    // In the order of the latest snapshot, tests that are gone aren't shown.
+    let names = snapshots
+        .last()
+        .map(|s| {
+            s.tests
+                .iter()
+                .map(|t| t.config.endpoint_name.clone())
+                .filter(|name| endpoint.as_ref().is_none_or(|e| e == name))
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+
+    for name in names {
+        let runs = snapshots
+            .iter()
+            .filter_map(|s| {
+                s.tests
+                    .iter()
+                    .find(|t| t.config.endpoint_name == name)
+                    .map(|t| (s, t))
+            })
+            .collect::<Vec<(&Snapshot, _)>>();
+        printer.blank_line().print_with_green(
+            "Trend",
+            &format!(
+                "{} ({} snapshots, {} to {})",
+                name,
+                runs.len(),
+                format_timestamp(runs[0].0.timestamp),
+                format_timestamp(runs[runs.len() - 1].0.timestamp)
+            ),
+            0,
+        );
+
+        let series = runs
+            .iter()
+            .map(|(_, test)| metrics(test, None))
+            .collect::<Vec<_>>();
+        for (index, metric) in series[0].iter().enumerate() {
+            let values = series.iter().map(|m| m[index].current).collect::<Vec<_>>();
+            let min = values.iter().cloned().fold(f64::INFINITY, f64::min);
+            let (first, latest) = (values[0], values[values.len() - 1]);
+            printer.print_plain(
+                &format!(
+                    "{:<18} {} {}{} -> {}{} ({})",
+                    metric.label,
+                    sparkline(&values.iter().map(|v| v - min).collect::<Vec<_>>()),
+                    first,
+                    metric.unit,
+                    latest,
+                    metric.unit,
+                    percent(first, latest)
+                ),
+                4,
+            );
+
+            let mut start = 0;
+            for step in steps(&values) {
+                let message = format!(
+                    "{} {:.1}{} -> {:.1}{} ({}) at {} {}",
+                    metric.label.to_lowercase(),
+                    step.before,
+                    metric.unit,
+                    step.after,
+                    metric.unit,
+                    percent(step.before, step.after),
+                    format_timestamp(runs[step.index].0.timestamp),
+                    runs[step.index].0.title()
+                );
+                match step.after > step.before {
+                    true => printer.print_with_red("Changed", &message, 6),
+                    false => printer.print_with_green("Changed", &message, 6),
+                };
+                start = step.index;
+            }
+
+            // Only the drift since the last change, it's where the endpoint is
+            // heading now.
+            if let Some(drift) = drift(&values[start..]) {
+                let message = format!(
+                    "{} {:+.2}{} per snapshot, {:+.1}% over the last {} snapshots",
+                    metric.label.to_lowercase(),
+                    drift.per_snapshot,
+                    metric.unit,
+                    drift.relative * 100.0,
+                    values.len() - start
+                );
+                match drift.per_snapshot > 0.0 {
+                    true => printer.print_with_red("Drifting", &message, 6),
+                    false => printer.print_with_green("Drifting", &message, 6),
+                };
+            }
+        }
+    }
+
+    Ok(())
+}